@@ -0,0 +1,85 @@
+/* mlfq.h
+ * C interface of the MLFQ simulator, exported by the mlfq cdylib (libmlfq.so, libmlfq.dylib
+ * or mlfq.dll). It mirrors src/ffi.rs, and tests/ffi_header.rs compiles it against the library.
+ * Author: Hank Bao
+ */
+
+#ifndef MLFQ_H
+#define MLFQ_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MLFQ_EVENT_RUN 0
+#define MLFQ_EVENT_FINISH 1
+
+/* Opaque scheduler handle */
+typedef struct MlfqScheduler MlfqScheduler;
+
+typedef struct MlfqQueueConfig {
+    uint32_t quantum;
+    uint32_t allotment;
+    bool push_front;
+} MlfqQueueConfig;
+
+typedef struct MlfqMetrics {
+    uint32_t idle_time;
+    uint32_t average_turnaround_time;
+    uint32_t average_response_time;
+    uint32_t makespan;
+    uint32_t p99_turnaround_time;
+    uint32_t max_wait_time;
+    uint32_t deadline_misses;
+} MlfqMetrics;
+
+/* A process ran from time for length ticks, or finished at time with length 0 */
+typedef struct MlfqEvent {
+    uint32_t kind;
+    uint32_t pid;
+    uint32_t time;
+    uint32_t length;
+} MlfqEvent;
+
+/* Create a scheduler from count queue configs, the highest priority first.
+ * Returns NULL if a config is invalid. Free it with mlfq_destroy. */
+MlfqScheduler *mlfq_create(const MlfqQueueConfig *queues,
+                           size_t count,
+                           uint32_t boost_interval,
+                           bool io_bump,
+                           bool io_stay);
+
+/* Free a scheduler and everything it holds. NULL is ignored. */
+void mlfq_destroy(MlfqScheduler *handle);
+
+/* Add a job, which gets the next pid starting at 0. Returns the pid, or -1 if the job is invalid. */
+int64_t mlfq_add_job(MlfqScheduler *handle,
+                     uint32_t arrival_time,
+                     uint32_t workload,
+                     uint32_t io_interval,
+                     uint32_t io_length);
+
+/* Run one scheduling decision. Returns 1 if jobs are left, 0 when all have finished, -1 on error. */
+int32_t mlfq_step(MlfqScheduler *handle);
+
+/* Returns 0 once every job has finished, -1 on error. */
+int32_t mlfq_run(MlfqScheduler *handle);
+
+/* The simulated time, 0 on error. */
+uint32_t mlfq_current_time(const MlfqScheduler *handle);
+
+/* Fill in the metrics of the jobs finished so far. Returns -1 if no job was added. */
+int32_t mlfq_metrics(const MlfqScheduler *handle, MlfqMetrics *out);
+
+/* Take the next event in time order. Returns 1 if one was written, 0 if there is none yet. */
+int32_t mlfq_next_event(MlfqScheduler *handle, MlfqEvent *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MLFQ_H */
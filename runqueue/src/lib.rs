@@ -0,0 +1,357 @@
+// lib.rs
+// Generic MLFQ run queue for kernels. It holds task handles rather than processes, is driven
+// by the kernel's timer tick and block and wake events, and needs nothing beyond alloc.
+// Author: Hank Bao
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+
+// What the run queue needs from a task: a cheap handle, e.g. a pointer or an index,
+// and an id that stays the same while the task exists
+pub trait TaskHandle: Clone {
+    type Id: Ord + Copy;
+
+    fn id(&self) -> Self::Id;
+}
+
+impl TaskHandle for usize {
+    type Id = usize;
+
+    fn id(&self) -> usize {
+        *self
+    }
+}
+
+impl TaskHandle for u32 {
+    type Id = u32;
+
+    fn id(&self) -> u32 {
+        *self
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct LevelConfig {
+    quantum: u32,
+    allotment: u32,
+}
+
+impl LevelConfig {
+    pub fn new(quantum: u32, allotment: u32) -> LevelConfig {
+        assert!(quantum > 0 && allotment > 0);
+        LevelConfig { quantum, allotment }
+    }
+
+    pub fn quantum(&self) -> u32 {
+        self.quantum
+    }
+
+    pub fn allotment(&self) -> u32 {
+        self.allotment
+    }
+}
+
+// Scheduling state of a task, kept while it is queued, running or blocked
+struct Entity {
+    level: usize,
+    allotment: u32,
+    slice: u32,
+}
+
+pub struct MlfqRunQueue<T: TaskHandle> {
+    levels: Vec<LevelConfig>,
+    queues: Vec<VecDeque<T>>,
+    entities: BTreeMap<T::Id, Entity>,
+    current: Option<T>,
+    boost_interval: u32,
+    ticks_since_boost: u32,
+    io_bump: bool,
+    io_stay: bool,
+}
+
+impl<T: TaskHandle> MlfqRunQueue<T> {
+    // Level 0 has the highest priority
+    pub fn new(levels: &[LevelConfig]) -> MlfqRunQueue<T> {
+        assert!(!levels.is_empty());
+        MlfqRunQueue {
+            levels: levels.to_vec(),
+            queues: (0..levels.len()).map(|_| VecDeque::new()).collect(),
+            entities: BTreeMap::new(),
+            current: None,
+            boost_interval: 0,
+            ticks_since_boost: 0,
+            io_bump: false,
+            io_stay: false,
+        }
+    }
+
+    // Boost every task to the top level every interval ticks, 0 for never
+    pub fn set_boost_interval(&mut self, interval: u32) {
+        self.boost_interval = interval;
+    }
+
+    // A woken task goes to the front of its queue instead of the tail
+    pub fn set_io_bump(&mut self, io_bump: bool) {
+        self.io_bump = io_bump;
+    }
+
+    // A task that blocks gets its allotment back, so it stays at its level
+    pub fn set_io_stay(&mut self, io_stay: bool) {
+        self.io_stay = io_stay;
+    }
+
+    pub fn levels(&self) -> &[LevelConfig] {
+        &self.levels
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        self.current.as_ref()
+    }
+
+    // The level of a known task, whether it is queued, running or blocked
+    pub fn level_of(&self, id: T::Id) -> Option<usize> {
+        self.entities.get(&id).map(|e| e.level)
+    }
+
+    // Number of queued tasks, not counting the running one
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(|q| q.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(|q| q.is_empty())
+    }
+
+    // A new task enters the highest queue (Rule 3)
+    pub fn enqueue(&mut self, task: T) {
+        let entity = Entity {
+            level: 0,
+            allotment: self.levels[0].allotment,
+            slice: 0,
+        };
+        self.entities.insert(task.id(), entity);
+        self.queues[0].push_back(task);
+    }
+
+    // Rules 1 and 2: the running task, if it is still runnable, goes to the tail of its queue,
+    // and the first task of the highest non-empty queue becomes the running task
+    pub fn pick_next(&mut self) -> Option<T> {
+        if let Some(task) = self.current.take() {
+            let level = self.entities[&task.id()].level;
+            self.queues[level].push_back(task);
+        }
+
+        let task = self.queues.iter_mut().find_map(|q| q.pop_front())?;
+        if let Some(entity) = self.entities.get_mut(&task.id()) {
+            entity.slice = 0;
+        }
+        self.current = Some(task.clone());
+        Some(task)
+    }
+
+    // Charge a timer tick to the running task. True if the kernel should call pick_next,
+    // because the quantum is up, the task was demoted, or a higher priority task is waiting.
+    pub fn on_tick(&mut self) -> bool {
+        let mut resched = false;
+        if self.boost_interval > 0 {
+            self.ticks_since_boost += 1;
+            if self.ticks_since_boost >= self.boost_interval {
+                self.boost();
+                resched = true;
+            }
+        }
+
+        let id = match self.current.as_ref() {
+            Some(task) => task.id(),
+            None => return resched || !self.is_empty(),
+        };
+
+        let last = self.levels.len() - 1;
+        let entity = self.entities.get_mut(&id).expect("running task unknown");
+        entity.slice += 1;
+        entity.allotment = entity.allotment.saturating_sub(1);
+
+        // Rule 4: the allotment is used up
+        if entity.allotment == 0 {
+            if entity.level < last {
+                entity.level += 1;
+            }
+            entity.allotment = self.levels[entity.level].allotment;
+            return true;
+        }
+
+        let level = entity.level;
+        resched
+            || entity.slice >= self.levels[level].quantum
+            || self.queues[..level].iter().any(|q| !q.is_empty())
+    }
+
+    // The running task blocks. It keeps its level until it is woken, and is returned.
+    pub fn on_block(&mut self) -> Option<T> {
+        let task = self.current.take()?;
+        if self.io_stay {
+            let entity = self
+                .entities
+                .get_mut(&task.id())
+                .expect("running task unknown");
+            entity.allotment = self.levels[entity.level].allotment;
+        }
+        Some(task)
+    }
+
+    // A blocked task is runnable again. True if it should preempt the running task.
+    pub fn on_wake(&mut self, task: T) -> bool {
+        let level = match self.entities.get(&task.id()) {
+            Some(entity) => entity.level,
+            None => {
+                self.enqueue(task);
+                return self.preempts(0);
+            }
+        };
+
+        // Waking a task that is not blocked does nothing
+        if self.current.as_ref().is_some_and(|t| t.id() == task.id())
+            || self.queues[level].iter().any(|t| t.id() == task.id())
+        {
+            return false;
+        }
+
+        if self.io_bump {
+            self.queues[level].push_front(task);
+        } else {
+            self.queues[level].push_back(task);
+        }
+        self.preempts(level)
+    }
+
+    fn preempts(&self, level: usize) -> bool {
+        match self.current.as_ref() {
+            Some(current) => level < self.entities[&current.id()].level,
+            None => true,
+        }
+    }
+
+    // The running task exits, and the run queue forgets it
+    pub fn on_exit(&mut self) -> Option<T> {
+        let task = self.current.take()?;
+        self.entities.remove(&task.id());
+        Some(task)
+    }
+
+    // Forget a task, e.g. one killed while queued, blocked or running. Removing the running
+    // task leaves none running, so the kernel should call pick_next.
+    pub fn remove(&mut self, id: T::Id) -> Option<T> {
+        self.entities.remove(&id)?;
+        if self.current.as_ref().is_some_and(|t| t.id() == id) {
+            return self.current.take();
+        }
+        self.queues.iter_mut().find_map(|q| {
+            let index = q.iter().position(|t| t.id() == id)?;
+            q.remove(index)
+        })
+    }
+
+    // Rule 5: move every task to the highest queue
+    pub fn boost(&mut self) {
+        self.ticks_since_boost = 0;
+        let allotment = self.levels[0].allotment;
+        for level in 1..self.queues.len() {
+            let moved = core::mem::take(&mut self.queues[level]);
+            self.queues[0].extend(moved);
+        }
+        for entity in self.entities.values_mut() {
+            entity.level = 0;
+            entity.allotment = allotment;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two levels, where level 0 has a 2 tick quantum and a 4 tick allotment
+    fn run_queue() -> MlfqRunQueue<u32> {
+        MlfqRunQueue::new(&[LevelConfig::new(2, 4), LevelConfig::new(4, 8)])
+    }
+
+    #[test]
+    fn demotes_after_allotment() {
+        let mut rq = run_queue();
+        rq.enqueue(1);
+        assert_eq!(rq.pick_next(), Some(1));
+
+        assert!(!rq.on_tick());
+        // The quantum is up, and the task is the only one to run again
+        assert!(rq.on_tick());
+        assert_eq!(rq.pick_next(), Some(1));
+        assert!(!rq.on_tick());
+        assert_eq!(rq.level_of(1), Some(0));
+        assert!(rq.on_tick());
+        assert_eq!(rq.level_of(1), Some(1));
+    }
+
+    #[test]
+    fn keeps_level_while_blocked() {
+        let mut rq = run_queue();
+        rq.set_io_stay(true);
+        rq.enqueue(1);
+        rq.pick_next();
+        for _ in 0..3 {
+            rq.on_tick();
+        }
+
+        // io_stay refills the allotment, so the task is not demoted on its next tick
+        assert_eq!(rq.on_block(), Some(1));
+        assert!(rq.is_empty());
+        assert!(rq.on_wake(1));
+        assert_eq!(rq.pick_next(), Some(1));
+        assert!(!rq.on_tick());
+        assert_eq!(rq.level_of(1), Some(0));
+    }
+
+    #[test]
+    fn boost_moves_every_task_to_the_top() {
+        let mut rq = run_queue();
+        rq.set_boost_interval(6);
+        rq.enqueue(1);
+        rq.pick_next();
+        for _ in 0..4 {
+            rq.on_tick();
+        }
+        assert_eq!(rq.level_of(1), Some(1));
+
+        rq.on_tick();
+        assert!(rq.on_tick());
+        assert_eq!(rq.level_of(1), Some(0));
+    }
+
+    #[test]
+    fn wake_while_running_does_nothing() {
+        let mut rq = run_queue();
+        rq.enqueue(1);
+        rq.pick_next();
+
+        assert!(!rq.on_wake(1));
+        assert!(rq.is_empty());
+        assert_eq!(rq.current(), Some(&1));
+    }
+
+    #[test]
+    fn remove_running_task() {
+        let mut rq = run_queue();
+        rq.enqueue(1);
+        rq.enqueue(2);
+        rq.pick_next();
+
+        assert_eq!(rq.remove(1), Some(1));
+        assert_eq!(rq.current(), None);
+        assert_eq!(rq.level_of(1), None);
+        assert!(rq.on_tick());
+        assert_eq!(rq.pick_next(), Some(2));
+    }
+}
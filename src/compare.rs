@@ -0,0 +1,363 @@
+// compare.rs
+// Side-by-side comparison of several named configurations on one workload.
+// Author: Hank Bao
+
+use crate::config::{JobConfig, QueueConfig, SchedulerConfig};
+use crate::dispatch::DispatchTable;
+use crate::metrics::Metrics;
+use crate::policy::{PolicyKind, PolicyOptions};
+use crate::scheduler::{Scheduler, Slice};
+
+pub struct NamedConfig {
+    name: String,
+    scheduler_config: SchedulerConfig,
+    queue_configs: Vec<QueueConfig>,
+    dispatch_table: Option<DispatchTable>,
+    policy: Option<PolicyKind>,
+}
+
+impl NamedConfig {
+    pub fn new(
+        name: &str,
+        scheduler_config: SchedulerConfig,
+        queue_configs: Vec<QueueConfig>,
+    ) -> NamedConfig {
+        NamedConfig {
+            name: name.to_string(),
+            scheduler_config,
+            queue_configs,
+            dispatch_table: None,
+            policy: None,
+        }
+    }
+
+    // A config that follows one of the built-in policies
+    pub fn with_policy(
+        name: &str,
+        scheduler_config: SchedulerConfig,
+        policy: PolicyKind,
+    ) -> NamedConfig {
+        NamedConfig {
+            name: name.to_string(),
+            scheduler_config,
+            queue_configs: Vec::new(),
+            dispatch_table: None,
+            policy: Some(policy),
+        }
+    }
+
+    // A config driven by a dispatch table instead of the queue configs
+    pub fn with_dispatch_table(
+        name: &str,
+        scheduler_config: SchedulerConfig,
+        table: DispatchTable,
+    ) -> NamedConfig {
+        NamedConfig {
+            name: name.to_string(),
+            scheduler_config,
+            queue_configs: table.queue_configs(),
+            dispatch_table: Some(table),
+            policy: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn scheduler(&self) -> Scheduler {
+        if let Some(policy) = self.policy {
+            return Scheduler::with_policy(
+                self.scheduler_config,
+                policy.create(&PolicyOptions::default()),
+            );
+        }
+
+        match &self.dispatch_table {
+            Some(table) => Scheduler::with_dispatch_table(self.scheduler_config, table.clone()),
+            None => Scheduler::new(self.scheduler_config, self.queue_configs.clone()),
+        }
+    }
+}
+
+// Resolve a config given as a preset name, or as NAME=SPEC where SPEC is a whitespace
+// separated list of overrides on top of the base: q=x,y,z a=x,y,z b=n front=true|false bump stay
+pub fn parse_config(spec: &str, base: &NamedConfig) -> Option<NamedConfig> {
+    let spec = spec.trim();
+    let (name, overrides) = match spec.split_once('=') {
+        Some((name, overrides)) => (name.trim(), overrides),
+        None => return preset(spec, base),
+    };
+
+    let mut quantums = base.queue_configs.iter().map(|q| q.quantum()).collect();
+    let mut allotments = base.queue_configs.iter().map(|q| q.allotment()).collect();
+    let mut push_front = base.queue_configs.first().is_none_or(|q| q.push_front());
+    let mut boost = base.scheduler_config.priority_boost_interval();
+    let mut io_bump = base.scheduler_config.io_bump();
+    let mut io_stay = base.scheduler_config.io_stay();
+
+    for item in overrides.split_whitespace() {
+        match item.split_once('=') {
+            Some(("q", values)) => quantums = parse_values(values)?,
+            Some(("a", values)) => allotments = parse_values(values)?,
+            Some(("b", value)) => boost = value.parse::<u32>().ok()?,
+            Some(("front", value)) => push_front = value.parse::<bool>().ok()?,
+            None if item == "bump" => io_bump = true,
+            None if item == "stay" => io_stay = true,
+            _ => {
+                eprintln!("Unknown setting {} in config {}.", item, name);
+                return None;
+            }
+        }
+    }
+
+    if quantums.len() != allotments.len() {
+        eprintln!(
+            "Config {} must have the same number of quantums and allotments.",
+            name
+        );
+        return None;
+    }
+
+    let queue_configs = std::iter::zip(quantums, allotments)
+        .map(|(quantum, allotment)| QueueConfig::new(quantum, allotment, push_front))
+        .collect();
+
+    Some(NamedConfig::new(
+        name,
+        SchedulerConfig::new(boost, io_bump, io_stay),
+        queue_configs,
+    ))
+}
+
+// Built-in configurations. ostep-default mirrors the defaults of the OSTEP mlfq.py simulator,
+// solaris-ts uses the default Solaris dispatch table, the names of the built-in policies
+// select that policy, and the others change one setting of the base.
+fn preset(name: &str, base: &NamedConfig) -> Option<NamedConfig> {
+    if let Ok(policy) = <PolicyKind as clap::ValueEnum>::from_str(name, true) {
+        return Some(NamedConfig::with_policy(
+            name,
+            SchedulerConfig::new(0, false, false),
+            policy,
+        ));
+    }
+
+    let base_config = base.scheduler_config;
+    let boost = base_config.priority_boost_interval();
+
+    let (scheduler_config, queue_configs) = match name {
+        "solaris-ts" => {
+            return Some(NamedConfig::with_dispatch_table(
+                name,
+                SchedulerConfig::new(0, false, false),
+                DispatchTable::solaris_default(),
+            ))
+        }
+        "ostep-default" => (
+            SchedulerConfig::new(0, false, false),
+            vec![QueueConfig::new(10, 10, true); 3],
+        ),
+        "base" => (base_config, base.queue_configs.clone()),
+        "no-boost" => (
+            SchedulerConfig::new(0, base_config.io_bump(), base_config.io_stay()),
+            base.queue_configs.clone(),
+        ),
+        "io-bump" => (
+            SchedulerConfig::new(boost, true, base_config.io_stay()),
+            base.queue_configs.clone(),
+        ),
+        "io-stay" => (
+            SchedulerConfig::new(boost, base_config.io_bump(), true),
+            base.queue_configs.clone(),
+        ),
+        _ => {
+            eprintln!(
+                "Unknown config {}. Use a preset (ostep-default, solaris-ts, base, no-boost, io-bump, io-stay), a policy name or NAME=SPEC.",
+                name
+            );
+            return None;
+        }
+    };
+
+    Some(NamedConfig::new(name, scheduler_config, queue_configs))
+}
+
+fn parse_values(values: &str) -> Option<Vec<u32>> {
+    let values = values
+        .split(',')
+        .map(|x| x.parse::<u32>().ok().filter(|&x| x > 0))
+        .collect::<Option<Vec<u32>>>();
+
+    if values.is_none() {
+        eprintln!("Quantums and allotments must be positive numbers.");
+    }
+    values
+}
+
+pub struct Outcome {
+    name: String,
+    metrics: Metrics,
+    // (pid, turnaround time), sorted by pid
+    turnarounds: Vec<(u32, u32)>,
+    slices: Vec<Slice>,
+}
+
+impl Outcome {
+    // The results of a scheduler that ran to completion
+    pub fn new(name: &str, scheduler: &Scheduler) -> Outcome {
+        let mut turnarounds = scheduler
+            .finished_processes()
+            .iter()
+            .map(|p| (p.pid(), p.turnaround_time()))
+            .collect::<Vec<(u32, u32)>>();
+        turnarounds.sort_unstable();
+
+        Outcome {
+            name: name.to_string(),
+            metrics: Metrics::from(scheduler),
+            turnarounds,
+            slices: scheduler.slices().to_vec(),
+        }
+    }
+}
+
+pub fn run_all(configs: &[NamedConfig], jobs: &[JobConfig]) -> Vec<Outcome> {
+    configs
+        .iter()
+        .map(|config| {
+            let mut scheduler = config.scheduler();
+            scheduler.set_trace(false);
+            scheduler.add_jobs(jobs.to_vec());
+            scheduler.run_to_completion();
+            Outcome::new(&config.name, &scheduler)
+        })
+        .collect()
+}
+
+pub fn print_report(outcomes: &[Outcome], baseline: usize) {
+    let base = &outcomes[baseline];
+    let name_width = outcomes
+        .iter()
+        .map(|o| o.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+
+    println!("Metrics, deltas against {}:", base.name);
+    println!(
+        "{:<w$}  {:>14} {:>14} {:>14} {:>14} {:>14} {:>14}",
+        "config",
+        "turnaround",
+        "response",
+        "p99 turnaround",
+        "max wait",
+        "idle",
+        "makespan",
+        w = name_width
+    );
+    for outcome in outcomes {
+        let m = &outcome.metrics;
+        let b = &base.metrics;
+        println!(
+            "{:<w$}  {:>14} {:>14} {:>14} {:>14} {:>14} {:>14}",
+            outcome.name,
+            with_delta(m.average_turnaround_time(), b.average_turnaround_time()),
+            with_delta(m.average_response_time(), b.average_response_time()),
+            with_delta(m.p99_turnaround_time(), b.p99_turnaround_time()),
+            with_delta(m.max_wait_time(), b.max_wait_time()),
+            with_delta(m.idle_time(), b.idle_time()),
+            with_delta(m.makespan(), b.makespan()),
+            w = name_width
+        );
+    }
+
+    if outcomes.iter().any(|o| o.metrics.max_lateness().is_some()) {
+        println!();
+        println!("Deadlines:");
+        println!(
+            "{:<w$}  {:>14} {:>14}",
+            "config",
+            "misses",
+            "max lateness",
+            w = name_width
+        );
+        for outcome in outcomes {
+            let m = &outcome.metrics;
+            println!(
+                "{:<w$}  {:>14} {:>14}",
+                outcome.name,
+                with_delta(m.deadline_misses(), base.metrics.deadline_misses()),
+                m.max_lateness().unwrap_or(0),
+                w = name_width
+            );
+        }
+    }
+
+    println!();
+    println!("Turnaround time per job, deltas against {}:", base.name);
+    print!("{:>5}", "pid");
+    for outcome in outcomes {
+        print!(" {:>w$}", outcome.name, w = name_width.max(14));
+    }
+    println!();
+    for (i, &(pid, base_turnaround)) in base.turnarounds.iter().enumerate() {
+        print!("{:>5}", pid);
+        for outcome in outcomes {
+            print!(
+                " {:>w$}",
+                with_delta(outcome.turnarounds[i].1, base_turnaround),
+                w = name_width.max(14)
+            );
+        }
+        println!();
+    }
+}
+
+fn with_delta(value: u32, base: u32) -> String {
+    let delta = value as i64 - base as i64;
+    if delta == 0 {
+        value.to_string()
+    } else {
+        format!("{} ({:+})", value, delta)
+    }
+}
+
+// Render one row per config, all on the same time scale so the rows line up
+pub fn print_timelines(outcomes: &[Outcome], width: usize) {
+    let name_width = outcomes.iter().map(|o| o.name.len()).max().unwrap_or(0);
+    let end = outcomes
+        .iter()
+        .map(|o| o.metrics.makespan())
+        .max()
+        .unwrap_or(0);
+    let scale = (end as usize).div_ceil(width.max(1)).max(1);
+
+    println!();
+    println!(
+        "Timeline, 1 column = {} ticks, '.' = idle, pids 10+ as letters:",
+        scale
+    );
+    for outcome in outcomes {
+        let mut row = String::new();
+        let mut slices = outcome.slices.iter().peekable();
+        for column in 0..(end as usize).div_ceil(scale) {
+            let tick = (column * scale) as u32;
+            while slices
+                .peek()
+                .is_some_and(|s| s.start() + s.length() <= tick)
+            {
+                slices.next();
+            }
+
+            row.push(match slices.peek() {
+                Some(s) if s.start() <= tick => pid_char(s.pid()),
+                _ => '.',
+            });
+        }
+        println!("{:<w$} |{}|", outcome.name, row, w = name_width);
+    }
+}
+
+fn pid_char(pid: u32) -> char {
+    std::char::from_digit(pid, 36).unwrap_or('#')
+}
@@ -2,6 +2,8 @@
 // Config store parameters for the MLFQ scheduler.
 // Author: Hank Bao
 
+use crate::snapshot::{self, Fields};
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct SchedulerConfig {
     io_bump: bool,
@@ -31,24 +33,187 @@ impl SchedulerConfig {
     }
 }
 
+// Scheduling class of a job, as in Linux. Real-time jobs always run before the MLFQ,
+// and idle jobs only run when nothing else can.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum SchedClass {
+    // Time-sharing, scheduled by the MLFQ
+    #[default]
+    Other,
+    // Real-time, runs until it blocks, finishes or a higher real-time priority preempts it
+    Fifo,
+    // Real-time with a time slice among jobs of the same priority
+    Rr,
+    // Below the MLFQ
+    Idle,
+}
+
+impl SchedClass {
+    pub fn is_real_time(&self) -> bool {
+        matches!(self, SchedClass::Fifo | SchedClass::Rr)
+    }
+}
+
+// Where a spawned child job enters the queues
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum ChildPlacement {
+    // The highest queue, like any new job (Rule 3)
+    #[default]
+    Top,
+    // The queue the parent runs in when it spawns the child
+    Inherit,
+}
+
+// A child job that a job spawns once it has done the given amount of work
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Spawn {
+    at: u32,
+    workload: u32,
+    io_interval: u32,
+    io_length: u32,
+}
+
+impl Spawn {
+    pub fn new(at: u32, workload: u32, io_interval: u32, io_length: u32) -> Spawn {
+        Spawn {
+            at,
+            workload,
+            io_interval,
+            io_length,
+        }
+    }
+
+    // Parse AT/WORKLOAD or AT/WORKLOAD/IO_INTERVAL/IO_LENGTH
+    pub fn parse(spec: &str) -> Option<Spawn> {
+        let values = spec
+            .split('/')
+            .map(|x| x.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        match values[..] {
+            [at, workload] if workload > 0 => Some(Spawn::new(at, workload, 0, 0)),
+            [at, workload, io_interval, io_length] if workload > 0 => {
+                Some(Spawn::new(at, workload, io_interval, io_length))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn spec(&self) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            self.at, self.workload, self.io_interval, self.io_length
+        )
+    }
+
+    pub fn at(&self) -> u32 {
+        self.at
+    }
+
+    pub fn workload(&self) -> u32 {
+        self.workload
+    }
+
+    pub fn io_interval(&self) -> u32 {
+        self.io_interval
+    }
+
+    pub fn io_length(&self) -> u32 {
+        self.io_length
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct JobConfig {
     arrival_time: u32,
     workload: u32,
     io_interval: u32,
     io_length: u32,
+    nice: i32,
+    tickets: u32,
+    currency: Option<String>,
+    transfer_to: Option<u32>,
+    class: SchedClass,
+    rt_priority: u32,
+    period: u32,
+    deadline: Option<u32>,
+    dependencies: Vec<u32>,
+    spawns: Vec<Spawn>,
 }
 
 impl JobConfig {
+    pub const DEFAULT_TICKETS: u32 = 100;
+
     pub fn new(arrival_time: u32, workload: u32, io_interval: u32, io_length: u32) -> JobConfig {
         JobConfig {
             arrival_time,
             workload,
             io_interval,
             io_length,
+            nice: 0,
+            tickets: JobConfig::DEFAULT_TICKETS,
+            currency: None,
+            transfer_to: None,
+            class: SchedClass::Other,
+            rt_priority: 0,
+            period: 0,
+            deadline: None,
+            dependencies: Vec::new(),
+            spawns: Vec::new(),
         }
     }
 
+    // Nice value from -20 (favored) to 19, used by the policies that weigh jobs
+    pub fn set_nice(&mut self, nice: i32) {
+        self.nice = nice;
+    }
+
+    // Tickets of the proportional-share policies, counted in the job's currency
+    pub fn set_tickets(&mut self, tickets: u32) {
+        self.tickets = tickets;
+    }
+
+    // The currency the tickets are issued in, None for the base currency
+    pub fn set_currency(&mut self, currency: Option<String>) {
+        self.currency = currency;
+    }
+
+    // The job that receives this job's tickets while it is blocked on I/O
+    pub fn set_transfer_to(&mut self, pid: Option<u32>) {
+        self.transfer_to = pid;
+    }
+
+    // Real-time priority from 1 to 99, higher runs first. Only used by the real-time classes.
+    pub fn set_class(&mut self, class: SchedClass, rt_priority: u32) {
+        self.class = class;
+        self.rt_priority = rt_priority;
+    }
+
+    pub fn set_arrival_time(&mut self, arrival_time: u32) {
+        self.arrival_time = arrival_time;
+    }
+
+    // Release an instance every period ticks from the arrival time, 0 for a one-off job.
+    // The workload is the worst-case execution time of every instance.
+    pub fn set_period(&mut self, period: u32) {
+        self.period = period;
+    }
+
+    // Deadline relative to the arrival (release) time
+    pub fn set_deadline(&mut self, deadline: Option<u32>) {
+        self.deadline = deadline;
+    }
+
+    // The pids of the jobs that must finish before this job is admitted
+    pub fn set_dependencies(&mut self, dependencies: Vec<u32>) {
+        self.dependencies = dependencies;
+    }
+
+    // Spawn a child job once this job has done the given amount of work
+    pub fn add_spawn(&mut self, spawn: Spawn) {
+        self.spawns.push(spawn);
+        self.spawns.sort_by_key(|s| s.at());
+    }
+
     pub fn arrival_time(&self) -> u32 {
         self.arrival_time
     }
@@ -64,6 +229,127 @@ impl JobConfig {
     pub fn io_interval(&self) -> u32 {
         self.io_interval
     }
+
+    pub fn nice(&self) -> i32 {
+        self.nice
+    }
+
+    pub fn tickets(&self) -> u32 {
+        self.tickets
+    }
+
+    pub fn currency(&self) -> Option<&str> {
+        self.currency.as_deref()
+    }
+
+    pub fn transfer_to(&self) -> Option<u32> {
+        self.transfer_to
+    }
+
+    pub fn class(&self) -> SchedClass {
+        self.class
+    }
+
+    pub fn rt_priority(&self) -> u32 {
+        self.rt_priority
+    }
+
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn deadline(&self) -> Option<u32> {
+        self.deadline
+    }
+
+    pub fn dependencies(&self) -> &[u32] {
+        &self.dependencies
+    }
+
+    pub fn spawns(&self) -> &[Spawn] {
+        &self.spawns
+    }
+
+    // The fields of a "waiting" or "task" snapshot line. The attributes only the policies use are left
+    // out, since a run with a policy cannot be saved.
+    pub fn snapshot(&self) -> String {
+        let dependencies = self
+            .dependencies
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<String>>();
+        let spawns = self
+            .spawns
+            .iter()
+            .map(|s| s.spec())
+            .collect::<Vec<String>>();
+        format!(
+            "{} {} {} {} {} {} {} {} {} {}",
+            self.arrival_time,
+            self.workload,
+            self.io_interval,
+            self.io_length,
+            self.tickets,
+            snapshot::name(self.class),
+            self.rt_priority,
+            snapshot::opt(self.deadline),
+            snapshot::opt((!dependencies.is_empty()).then(|| dependencies.join(","))),
+            snapshot::opt((!spawns.is_empty()).then(|| spawns.join(",")))
+        )
+    }
+
+    pub fn from_snapshot(fields: &mut Fields) -> Option<JobConfig> {
+        let mut job = JobConfig::new(fields.u32()?, fields.u32()?, fields.u32()?, fields.u32()?);
+        job.set_tickets(fields.u32()?);
+        job.set_class(fields.value()?, fields.u32()?);
+        job.set_deadline(fields.opt_u32()?);
+        match fields.word()? {
+            "-" => {}
+            dependencies => job.set_dependencies(
+                dependencies
+                    .split(',')
+                    .map(|d| d.parse().ok())
+                    .collect::<Option<Vec<u32>>>()?,
+            ),
+        }
+        match fields.word()? {
+            "-" => {}
+            spawns => {
+                for spawn in spawns.split(',') {
+                    job.add_spawn(Spawn::parse(spawn)?);
+                }
+            }
+        }
+        fields.end()?;
+        Some(job)
+    }
+}
+
+// How a queue picks among its schedulable jobs
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum Discipline {
+    // The first schedulable job by position, i.e. round-robin
+    #[default]
+    #[value(name = "rr")]
+    RoundRobin,
+    // The job that arrived first, run until it blocks or finishes regardless of the quantum
+    Fcfs,
+    // The job with the least work left
+    Srt,
+    // A random job, weighted by its tickets
+    Lottery,
+}
+
+impl Discipline {
+    // The order of the ready jobs, for --explain
+    pub fn order(&self) -> &'static str {
+        match self {
+            Discipline::RoundRobin => "round-robin",
+            Discipline::Fcfs => "first come, first served",
+            Discipline::Srt => "shortest remaining time",
+            Discipline::Lottery => "lottery",
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -71,6 +357,9 @@ pub struct QueueConfig {
     quantum: u32,
     allotment: u32,
     push_front: bool,
+    aging_threshold: u32,
+    aging_target: Option<usize>,
+    discipline: Discipline,
 }
 
 impl QueueConfig {
@@ -79,9 +368,23 @@ impl QueueConfig {
             quantum,
             allotment,
             push_front,
+            aging_threshold: 0,
+            aging_target: None,
+            discipline: Discipline::RoundRobin,
         }
     }
 
+    // Promote a job that waited at this level for `threshold` ticks without running,
+    // to `target` or one level up if no target is given. A threshold of 0 disables aging.
+    pub fn set_aging(&mut self, threshold: u32, target: Option<usize>) {
+        self.aging_threshold = threshold;
+        self.aging_target = target;
+    }
+
+    pub fn set_discipline(&mut self, discipline: Discipline) {
+        self.discipline = discipline;
+    }
+
     pub fn quantum(&self) -> u32 {
         self.quantum
     }
@@ -93,4 +396,16 @@ impl QueueConfig {
     pub fn push_front(&self) -> bool {
         self.push_front
     }
+
+    pub fn aging_threshold(&self) -> u32 {
+        self.aging_threshold
+    }
+
+    pub fn aging_target(&self) -> Option<usize> {
+        self.aging_target
+    }
+
+    pub fn discipline(&self) -> Discipline {
+        self.discipline
+    }
 }
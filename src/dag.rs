@@ -0,0 +1,101 @@
+// dag.rs
+// Workloads where jobs depend on each other, like a build graph or a pipeline.
+// A job is admitted only after the jobs it depends on finish, and its pid is its index.
+// Author: Hank Bao
+
+use crate::config::JobConfig;
+
+// The jobs in an order where every job comes after its dependencies,
+// None if a dependency is unknown or the dependencies form a cycle
+pub fn topological_order(jobs: &[JobConfig]) -> Option<Vec<usize>> {
+    let mut pending = Vec::with_capacity(jobs.len());
+    for job in jobs.iter() {
+        if job.dependencies().iter().any(|&d| d as usize >= jobs.len()) {
+            return None;
+        }
+        pending.push(job.dependencies().len());
+    }
+
+    let mut order = (0..jobs.len())
+        .filter(|&i| pending[i] == 0)
+        .collect::<Vec<usize>>();
+    let mut next = 0;
+    while next < order.len() {
+        let done = order[next] as u32;
+        next += 1;
+        for (i, job) in jobs.iter().enumerate() {
+            let count = job.dependencies().iter().filter(|&&d| d == done).count();
+            if count > 0 {
+                pending[i] -= count;
+                if pending[i] == 0 {
+                    order.push(i);
+                }
+            }
+        }
+    }
+
+    if order.len() == jobs.len() {
+        Some(order)
+    } else {
+        None
+    }
+}
+
+// The shortest time a job can take with a CPU to itself: its work and every I/O it issues.
+// A job does not issue I/O at the very end of its work.
+pub fn min_duration(job: &JobConfig) -> u32 {
+    let ios = match job.io_interval() {
+        0 => 0,
+        interval => (job.workload() - 1) / interval,
+    };
+    job.workload() + ios * job.io_length()
+}
+
+// The chain of jobs that finishes last with unlimited CPUs, and the time it finishes at,
+// a lower bound of the makespan. The jobs must have a topological order.
+pub fn critical_path(jobs: &[JobConfig]) -> (u32, Vec<usize>) {
+    let order = match topological_order(jobs) {
+        Some(order) => order,
+        None => return (0, Vec::new()),
+    };
+
+    // The earliest finish time of every job, and the dependency that held it back the longest
+    let mut finish = vec![0u32; jobs.len()];
+    let mut previous = vec![None; jobs.len()];
+    for &i in order.iter() {
+        let job = &jobs[i];
+        let mut start = job.arrival_time();
+        for &d in job.dependencies() {
+            if finish[d as usize] > start {
+                start = finish[d as usize];
+                previous[i] = Some(d as usize);
+            }
+        }
+        finish[i] = start + min_duration(job);
+    }
+
+    let mut last = match (0..jobs.len()).max_by_key(|&i| finish[i]) {
+        Some(last) => last,
+        None => return (0, Vec::new()),
+    };
+    let length = finish[last];
+
+    let mut path = vec![last];
+    while let Some(p) = previous[last] {
+        path.push(p);
+        last = p;
+    }
+    path.reverse();
+    (length, path)
+}
+
+pub fn print_report(jobs: &[JobConfig], makespan: u32) {
+    let (length, path) = critical_path(jobs);
+    let path = path.iter().map(|p| p.to_string()).collect::<Vec<String>>();
+    println!(
+        "Makespan: {}. Critical path length: {} ({}).",
+        makespan,
+        length,
+        path.join(" -> ")
+    );
+}
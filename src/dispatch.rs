@@ -0,0 +1,226 @@
+// dispatch.rs
+// Solaris time-sharing (TS) dispatch table, an alternative to per-queue quantum and allotment.
+// Author: Hank Bao
+
+use crate::config::QueueConfig;
+
+// One row of the table. Priorities follow Solaris, where a larger number is a higher priority.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct DispatchEntry {
+    quantum: u32,
+    tqexp: usize,
+    slpret: usize,
+    maxwait: u32,
+    lwait: usize,
+}
+
+impl DispatchEntry {
+    pub fn new(quantum: u32, tqexp: usize, slpret: usize, maxwait: u32, lwait: usize) -> Self {
+        DispatchEntry {
+            quantum,
+            tqexp,
+            slpret,
+            maxwait,
+            lwait,
+        }
+    }
+
+    // Time slice at this priority
+    pub fn quantum(&self) -> u32 {
+        self.quantum
+    }
+
+    // New priority when the time slice expires
+    pub fn tqexp(&self) -> usize {
+        self.tqexp
+    }
+
+    // New priority when returning from sleep
+    pub fn slpret(&self) -> usize {
+        self.slpret
+    }
+
+    // Number of update intervals a job may wait before being aged
+    pub fn maxwait(&self) -> u32 {
+        self.maxwait
+    }
+
+    // New priority when the job waited longer than maxwait
+    pub fn lwait(&self) -> usize {
+        self.lwait
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DispatchTable {
+    entries: Vec<DispatchEntry>,
+    update_interval: u32,
+}
+
+impl DispatchTable {
+    // Ticks between two aging passes, Solaris runs ts_update once per second
+    pub const DEFAULT_UPDATE_INTERVAL: u32 = 1000;
+
+    // Create a table, None if it is empty or any target priority is out of range
+    pub fn new(entries: Vec<DispatchEntry>, update_interval: u32) -> Option<DispatchTable> {
+        let levels = entries.len();
+        let valid = levels > 0
+            && update_interval > 0
+            && entries.iter().all(|e| {
+                e.quantum > 0 && e.tqexp < levels && e.slpret < levels && e.lwait < levels
+            });
+
+        if !valid {
+            return None;
+        }
+
+        Some(DispatchTable {
+            entries,
+            update_interval,
+        })
+    }
+
+    // The default ts_dptbl of Solaris 10, with one tick per millisecond
+    pub fn solaris_default() -> DispatchTable {
+        let mut entries = Vec::with_capacity(60);
+        for p in 0..60usize {
+            let entry = match p {
+                0..=9 => DispatchEntry::new(200, 0, 50, 0, 50),
+                10..=19 => DispatchEntry::new(160, p - 10, 51, 0, 51),
+                20..=29 => DispatchEntry::new(120, p - 10, 52, 0, 52),
+                30..=39 => DispatchEntry::new(80, p - 10, 53, 0, 53),
+                40..=44 => DispatchEntry::new(40, p - 10, 55, 0, 55),
+                45 => DispatchEntry::new(40, 35, 56, 0, 56),
+                46 => DispatchEntry::new(40, 36, 57, 0, 57),
+                47..=48 => DispatchEntry::new(40, p - 10, 58, 0, 58),
+                49..=58 => DispatchEntry::new(40, p - 10, 58, 0, 59),
+                _ => DispatchEntry::new(20, 49, 59, 32000, 59),
+            };
+            entries.push(entry);
+        }
+
+        DispatchTable {
+            entries,
+            update_interval: Self::DEFAULT_UPDATE_INTERVAL,
+        }
+    }
+
+    // Parse a table in the format printed by `dispadmin -c TS -g`: an optional RES=n line
+    // giving the resolution of the quantums, then one row of five numbers per priority from 0 up.
+    // Everything after '#' is a comment.
+    pub fn parse(text: &str) -> Option<DispatchTable> {
+        let mut resolution = 1000u64;
+        let mut entries = Vec::new();
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(res) = line.strip_prefix("RES=") {
+                resolution = res.trim().parse::<u64>().ok().filter(|&r| r > 0)?;
+                continue;
+            }
+
+            let fields = line
+                .split_whitespace()
+                .map(|x| x.parse::<u32>().ok())
+                .collect::<Option<Vec<u32>>>()?;
+            if fields.len() != 5 {
+                return None;
+            }
+
+            // Convert the quantum to ticks of one millisecond
+            let quantum = (fields[0] as u64 * 1000 / resolution) as u32;
+            entries.push(DispatchEntry::new(
+                quantum,
+                fields[1] as usize,
+                fields[2] as usize,
+                fields[3],
+                fields[4] as usize,
+            ));
+        }
+
+        DispatchTable::new(entries, Self::DEFAULT_UPDATE_INTERVAL)
+    }
+
+    pub fn set_update_interval(&mut self, update_interval: u32) {
+        assert!(update_interval > 0);
+        self.update_interval = update_interval;
+    }
+
+    pub fn update_interval(&self) -> u32 {
+        self.update_interval
+    }
+
+    pub fn levels(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn entry(&self, priority: usize) -> &DispatchEntry {
+        &self.entries[priority]
+    }
+
+    // Queue 0 holds the highest priority, so priorities map onto queues in reverse
+    pub fn queue_of(&self, priority: usize) -> usize {
+        self.entries.len() - 1 - priority
+    }
+
+    pub fn priority_of(&self, queue: usize) -> usize {
+        self.entries.len() - 1 - queue
+    }
+
+    // One queue per priority, highest first. The allotment equals the quantum,
+    // since every expiry moves the job according to ts_tqexp.
+    pub fn queue_configs(&self) -> Vec<QueueConfig> {
+        self.entries
+            .iter()
+            .rev()
+            .map(|e| QueueConfig::new(e.quantum, e.quantum, false))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solaris_default_matches_ts_dptbl() {
+        // Rows of the ts_dptbl shipped with Solaris, as printed by `dispadmin -c TS -g`:
+        // ts_quantum ts_tqexp ts_slpret ts_maxwait ts_lwait PRIORITY
+        let published = [
+            (200, 0, 50, 0, 50, 0),
+            (160, 0, 51, 0, 51, 10),
+            (120, 10, 52, 0, 52, 20),
+            (80, 29, 53, 0, 53, 39),
+            (40, 30, 55, 0, 55, 40),
+            (40, 34, 55, 0, 55, 44),
+            (40, 35, 56, 0, 56, 45),
+            (40, 36, 57, 0, 57, 46),
+            (40, 38, 58, 0, 58, 48),
+            (40, 39, 58, 0, 59, 49),
+            (40, 48, 58, 0, 59, 58),
+            (20, 49, 59, 32000, 59, 59),
+        ];
+
+        let table = DispatchTable::solaris_default();
+        assert_eq!(table.levels(), 60);
+        for (quantum, tqexp, slpret, maxwait, lwait, priority) in published {
+            let entry = table.entry(priority);
+            assert_eq!(
+                (
+                    entry.quantum(),
+                    entry.tqexp(),
+                    entry.slpret(),
+                    entry.maxwait(),
+                    entry.lwait()
+                ),
+                (quantum, tqexp, slpret, maxwait, lwait),
+                "priority {}",
+                priority
+            );
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// events.rs
+// External events injected into a run at given times, e.g. an administrator killing a job
+// or changing the settings of the scheduler. The scheduler applies them when they are due.
+// Author: Hank Bao
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EventKind {
+    Kill(u32),
+    Suspend(u32),
+    Resume(u32),
+    // Move a job to a queue level
+    Move(u32, usize),
+    // Change the quantum of a queue level
+    Quantum(usize, u32),
+    // Change the priority boost interval, 0 to disable boosting
+    BoostInterval(u32),
+    Boost,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Event {
+    time: u32,
+    kind: EventKind,
+}
+
+impl Event {
+    pub fn new(time: u32, kind: EventKind) -> Event {
+        Event { time, kind }
+    }
+
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    pub fn kind(&self) -> EventKind {
+        self.kind
+    }
+
+    // Parse an event in the form TIME,ACTION[,ARGS], where the action is kill,PID,
+    // suspend,PID, resume,PID, move,PID,LEVEL, quantum,LEVEL,TICKS, boost-interval,TICKS or boost
+    pub fn parse(spec: &str) -> Option<Event> {
+        let fields = spec.split(',').map(|f| f.trim()).collect::<Vec<&str>>();
+        let numbers = match fields
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != 1)
+            .map(|(_, f)| f.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()
+        {
+            Some(numbers) if fields.len() >= 2 => numbers,
+            _ => {
+                eprintln!("Invalid event {}, expected TIME,ACTION[,ARGS].", spec);
+                return None;
+            }
+        };
+
+        let kind = match (fields[1], &numbers[1..]) {
+            ("kill", &[pid]) => EventKind::Kill(pid),
+            ("suspend", &[pid]) => EventKind::Suspend(pid),
+            ("resume", &[pid]) => EventKind::Resume(pid),
+            ("move", &[pid, level]) => EventKind::Move(pid, level as usize),
+            ("quantum", &[level, quantum]) if quantum > 0 => {
+                EventKind::Quantum(level as usize, quantum)
+            }
+            ("boost-interval", &[interval]) => EventKind::BoostInterval(interval),
+            ("boost", &[]) => EventKind::Boost,
+            _ => {
+                eprintln!(
+                    "Invalid event {}. Use kill,PID, suspend,PID, resume,PID, move,PID,LEVEL, quantum,LEVEL,TICKS, boost-interval,TICKS or boost.",
+                    spec
+                );
+                return None;
+            }
+        };
+
+        Some(Event::new(numbers[0], kind))
+    }
+
+    // The event in the form it is parsed from
+    pub fn spec(&self) -> String {
+        let action = match self.kind {
+            EventKind::Kill(pid) => format!("kill,{}", pid),
+            EventKind::Suspend(pid) => format!("suspend,{}", pid),
+            EventKind::Resume(pid) => format!("resume,{}", pid),
+            EventKind::Move(pid, level) => format!("move,{},{}", pid, level),
+            EventKind::Quantum(level, quantum) => format!("quantum,{},{}", level, quantum),
+            EventKind::BoostInterval(interval) => format!("boost-interval,{}", interval),
+            EventKind::Boost => "boost".to_string(),
+        };
+        format!("{},{}", self.time, action)
+    }
+}
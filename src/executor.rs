@@ -0,0 +1,779 @@
+// executor.rs
+// Executors for futures that schedule their tasks with the MLFQ rules. Every poll is charged
+// against the task's quantum and allotment, counted in polls or in time spent polling.
+// A task that wakes itself is still runnable and goes back to its queue, while a task that
+// returns Pending without being woken waits on I/O and keeps its level until it is woken.
+// Author: Hank Bao
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{QueueConfig, SchedulerConfig};
+
+type LocalFuture = Pin<Box<dyn Future<Output = ()>>>;
+type SendFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// What a quantum, an allotment and the boost interval are measured in
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Accounting {
+    // Every poll costs one tick, and the boost interval counts polls
+    Polls,
+    // A tick is the given time spent polling, and the boost interval is wall-clock time
+    Time(Duration),
+}
+
+impl Accounting {
+    // Ticks in the unit charged for a poll
+    fn units(&self, ticks: u32) -> u64 {
+        match self {
+            Accounting::Polls => ticks as u64,
+            Accounting::Time(tick) => ticks as u64 * tick.as_nanos().max(1) as u64,
+        }
+    }
+
+    fn cost(&self, elapsed: Duration) -> u64 {
+        match self {
+            Accounting::Polls => 1,
+            Accounting::Time(_) => (elapsed.as_nanos() as u64).max(1),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum TaskState {
+    // Pending and not woken, waiting on I/O
+    Idle,
+    Queued,
+    // Being polled, woken is set when the waker is called meanwhile
+    Running { woken: bool },
+}
+
+struct Task {
+    state: TaskState,
+    level: usize,
+    allotment: u64,
+}
+
+// Counters of an executor's scheduling decisions
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Stats {
+    pub polls: u64,
+    pub demotions: u64,
+    pub boosts: u64,
+    pub completed: u64,
+}
+
+// The MLFQ state shared by both executors, always used under its mutex
+struct Core {
+    config: SchedulerConfig,
+    queue_configs: Vec<QueueConfig>,
+    accounting: Accounting,
+    queues: Vec<VecDeque<u64>>,
+    tasks: HashMap<u64, Task>,
+    next_id: u64,
+    epoch: Instant,
+    polls: u64,
+    last_boost: u64,
+    stats: Stats,
+    shutdown: bool,
+}
+
+impl Core {
+    fn new(
+        config: SchedulerConfig,
+        queue_configs: Vec<QueueConfig>,
+        accounting: Accounting,
+    ) -> Core {
+        assert!(!queue_configs.is_empty());
+        Core {
+            queues: vec![VecDeque::new(); queue_configs.len()],
+            config,
+            queue_configs,
+            accounting,
+            tasks: HashMap::new(),
+            next_id: 0,
+            epoch: Instant::now(),
+            polls: 0,
+            last_boost: 0,
+            stats: Stats::default(),
+            shutdown: false,
+        }
+    }
+
+    fn allotment(&self, level: usize) -> u64 {
+        self.accounting.units(self.queue_configs[level].allotment())
+    }
+
+    fn quantum(&self, level: usize) -> u64 {
+        self.accounting.units(self.queue_configs[level].quantum())
+    }
+
+    // The clock of the boost interval
+    fn now(&self) -> u64 {
+        match self.accounting {
+            Accounting::Polls => self.polls,
+            Accounting::Time(_) => self.epoch.elapsed().as_nanos() as u64,
+        }
+    }
+
+    // A new task enters the highest queue (Rule 3)
+    fn add(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let task = Task {
+            state: TaskState::Queued,
+            level: 0,
+            allotment: self.allotment(0),
+        };
+        self.tasks.insert(id, task);
+        self.queues[0].push_back(id);
+        id
+    }
+
+    fn wake(&mut self, id: u64) -> bool {
+        let io_bump = self.config.io_bump();
+        let task = match self.tasks.get_mut(&id) {
+            Some(task) => task,
+            None => return false,
+        };
+
+        match task.state {
+            TaskState::Idle => {
+                task.state = TaskState::Queued;
+                if io_bump {
+                    self.queues[task.level].push_front(id);
+                } else {
+                    self.queues[task.level].push_back(id);
+                }
+                true
+            }
+            TaskState::Running { .. } => {
+                task.state = TaskState::Running { woken: true };
+                false
+            }
+            TaskState::Queued => false,
+        }
+    }
+
+    // Rule 5: move every task to the highest queue
+    fn boost_if_due(&mut self) {
+        let interval = self.accounting.units(self.config.priority_boost_interval());
+        let now = self.now();
+        if interval == 0 || now - self.last_boost < interval {
+            return;
+        }
+
+        self.last_boost = now;
+        self.stats.boosts += 1;
+        let allotment = self.allotment(0);
+        for level in 1..self.queues.len() {
+            let moved = std::mem::take(&mut self.queues[level]);
+            self.queues[0].extend(moved);
+        }
+        for task in self.tasks.values_mut() {
+            task.level = 0;
+            task.allotment = allotment;
+        }
+    }
+
+    // Rules 1 and 2: the first task of the highest non-empty queue, and its quantum
+    fn next(&mut self) -> Option<(u64, u64)> {
+        self.boost_if_due();
+        let id = self.queues.iter_mut().find_map(|q| q.pop_front())?;
+        let task = self.tasks.get_mut(&id).expect("queued task is gone");
+        task.state = TaskState::Running { woken: false };
+        let level = task.level;
+        Some((id, self.quantum(level)))
+    }
+
+    // Charge a poll, true if the task may be polled again in the same quantum
+    fn ran(&mut self, id: u64, cost: u64, slice: u64, quantum: u64, done: bool) -> bool {
+        self.polls += 1;
+        self.stats.polls += 1;
+        if done {
+            self.tasks.remove(&id);
+            self.stats.completed += 1;
+            return false;
+        }
+
+        let last = self.queues.len() - 1;
+        let io_stay = self.config.io_stay();
+        let mut task = self.tasks.remove(&id).expect("running task is gone");
+        let woken = task.state == (TaskState::Running { woken: true });
+        task.allotment = task.allotment.saturating_sub(cost);
+
+        // Rule 4, unless the task waits on I/O and io_stay keeps it
+        let mut demoted = false;
+        if task.allotment == 0 {
+            if task.level < last && (woken || !io_stay) {
+                task.level += 1;
+                demoted = true;
+                self.stats.demotions += 1;
+            }
+            task.allotment = self.allotment(task.level);
+        }
+
+        let level = task.level;
+        let preempted = self.queues[..level].iter().any(|q| !q.is_empty());
+        let again = woken && !demoted && !preempted && slice < quantum;
+        task.state = if again {
+            TaskState::Running { woken: false }
+        } else if woken {
+            self.queues[level].push_back(id);
+            TaskState::Queued
+        } else {
+            TaskState::Idle
+        };
+        self.tasks.insert(id, task);
+
+        self.boost_if_due();
+        again
+    }
+}
+
+struct Shared {
+    core: Mutex<Core>,
+    ready: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Core> {
+        self.core.lock().expect("executor state poisoned")
+    }
+}
+
+struct TaskWaker {
+    id: u64,
+    shared: Arc<Shared>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if self.shared.lock().wake(self.id) {
+            self.shared.ready.notify_one();
+        }
+    }
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    waiter: Option<Waker>,
+}
+
+// Output of a spawned task, which can be awaited by another task or taken after the run
+pub struct JoinHandle<T> {
+    state: Arc<Mutex<JoinState<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    fn new() -> (JoinHandle<T>, Arc<Mutex<JoinState<T>>>) {
+        let state = Arc::new(Mutex::new(JoinState {
+            output: None,
+            waiter: None,
+        }));
+        (
+            JoinHandle {
+                state: state.clone(),
+            },
+            state,
+        )
+    }
+
+    // The output, None if the task has not finished
+    pub fn try_take(&self) -> Option<T> {
+        self.state.lock().unwrap().output.take()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().unwrap();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waiter = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+// The future run as the task, storing the output where the handle finds it
+async fn complete<F: Future>(future: F, state: Arc<Mutex<JoinState<F::Output>>>) {
+    let output = future.await;
+    let waiter = {
+        let mut state = state.lock().unwrap();
+        state.output = Some(output);
+        state.waiter.take()
+    };
+    if let Some(waiter) = waiter {
+        waiter.wake();
+    }
+}
+
+// Poll the task once, returning whether it completed and how long the poll took
+fn poll_task<F: Future<Output = ()> + ?Sized>(
+    future: Pin<&mut F>,
+    id: u64,
+    shared: &Arc<Shared>,
+) -> (bool, Duration) {
+    let waker = Waker::from(Arc::new(TaskWaker {
+        id,
+        shared: shared.clone(),
+    }));
+    let mut cx = Context::from_waker(&waker);
+    let start = Instant::now();
+    let done = future.poll(&mut cx).is_ready();
+    (done, start.elapsed())
+}
+
+// Single-threaded executor. Tasks need not be Send, and are polled on the thread that runs it.
+pub struct Executor {
+    shared: Arc<Shared>,
+    futures: Rc<RefCell<HashMap<u64, LocalFuture>>>,
+}
+
+// Spawns tasks on an Executor from inside its tasks
+#[derive(Clone)]
+pub struct LocalSpawner {
+    shared: Arc<Shared>,
+    futures: Rc<RefCell<HashMap<u64, LocalFuture>>>,
+}
+
+impl LocalSpawner {
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+    {
+        let (handle, state) = JoinHandle::new();
+        let id = self.shared.lock().add();
+        self.futures
+            .borrow_mut()
+            .insert(id, Box::pin(complete(future, state)));
+        handle
+    }
+}
+
+impl Executor {
+    pub fn new(
+        config: SchedulerConfig,
+        queue_configs: Vec<QueueConfig>,
+        accounting: Accounting,
+    ) -> Executor {
+        Executor {
+            shared: Arc::new(Shared {
+                core: Mutex::new(Core::new(config, queue_configs, accounting)),
+                ready: Condvar::new(),
+            }),
+            futures: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    pub fn spawner(&self) -> LocalSpawner {
+        LocalSpawner {
+            shared: self.shared.clone(),
+            futures: self.futures.clone(),
+        }
+    }
+
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+    {
+        self.spawner().spawn(future)
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.shared.lock().stats
+    }
+
+    // Run until every task has completed
+    pub fn run(&mut self) {
+        self.run_until(|| false);
+    }
+
+    // Run the future as a task along with the others, until it completes
+    pub fn block_on<F>(&mut self, future: F) -> F::Output
+    where
+        F: Future + 'static,
+    {
+        let handle = self.spawn(future);
+        let state = handle.state.clone();
+        self.run_until(move || state.lock().unwrap().output.is_some());
+        handle
+            .try_take()
+            .expect("every task is waiting, but the future has not completed")
+    }
+
+    fn run_until(&mut self, done: impl Fn() -> bool) {
+        let accounting = self.shared.lock().accounting;
+        while !done() {
+            let (id, quantum) = {
+                let mut core = self.shared.lock();
+                loop {
+                    if let Some(next) = core.next() {
+                        break next;
+                    }
+                    // Every task is waiting on I/O, or none is left
+                    if core.tasks.is_empty() {
+                        return;
+                    }
+                    core = self.shared.ready.wait(core).unwrap();
+                }
+            };
+
+            let mut future = self
+                .futures
+                .borrow_mut()
+                .remove(&id)
+                .expect("task without a future");
+            let mut slice = 0;
+            loop {
+                let (completed, elapsed) = poll_task(future.as_mut(), id, &self.shared);
+                let cost = accounting.cost(elapsed);
+                slice += cost;
+                let again = self.shared.lock().ran(id, cost, slice, quantum, completed);
+                if completed {
+                    break;
+                }
+                if !again {
+                    self.futures.borrow_mut().insert(id, future);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+struct MultiShared {
+    shared: Arc<Shared>,
+    futures: Mutex<HashMap<u64, SendFuture>>,
+    idle: Condvar,
+}
+
+// Multi-threaded executor. Worker threads take the highest priority task there is, so a task
+// is only polled by one thread at a time and follows the same rules as on a single thread.
+pub struct MultiExecutor {
+    inner: Arc<MultiShared>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+// Spawns tasks on a MultiExecutor, from any thread
+#[derive(Clone)]
+pub struct Spawner {
+    inner: Arc<MultiShared>,
+}
+
+impl Spawner {
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        let (handle, state) = JoinHandle::new();
+        // The future is stored before the task can be picked
+        let mut core = self.inner.shared.lock();
+        let id = core.next_id;
+        self.inner
+            .futures
+            .lock()
+            .unwrap()
+            .insert(id, Box::pin(complete(future, state)));
+        core.add();
+        drop(core);
+        self.inner.shared.ready.notify_one();
+        handle
+    }
+}
+
+impl MultiExecutor {
+    pub fn new(
+        config: SchedulerConfig,
+        queue_configs: Vec<QueueConfig>,
+        accounting: Accounting,
+        threads: usize,
+    ) -> MultiExecutor {
+        assert!(threads > 0);
+        let inner = Arc::new(MultiShared {
+            shared: Arc::new(Shared {
+                core: Mutex::new(Core::new(config, queue_configs, accounting)),
+                ready: Condvar::new(),
+            }),
+            futures: Mutex::new(HashMap::new()),
+            idle: Condvar::new(),
+        });
+
+        let workers = (0..threads)
+            .map(|i| {
+                let inner = inner.clone();
+                thread::Builder::new()
+                    .name(format!("mlfq-worker-{}", i))
+                    .spawn(move || Self::work(&inner))
+                    .expect("failed to start a worker thread")
+            })
+            .collect();
+
+        MultiExecutor { inner, workers }
+    }
+
+    pub fn spawner(&self) -> Spawner {
+        Spawner {
+            inner: self.inner.clone(),
+        }
+    }
+
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        self.spawner().spawn(future)
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.inner.shared.lock().stats
+    }
+
+    // Block until every task has completed
+    pub fn wait(&self) {
+        let mut core = self.inner.shared.lock();
+        while !core.tasks.is_empty() {
+            core = self.inner.idle.wait(core).unwrap();
+        }
+    }
+
+    // Wait for every task, then stop the workers
+    pub fn shutdown(mut self) -> Stats {
+        self.wait();
+        self.stop();
+        self.stats()
+    }
+
+    fn stop(&mut self) {
+        self.inner.shared.lock().shutdown = true;
+        self.inner.shared.ready.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+
+    fn work(inner: &MultiShared) {
+        let shared = &inner.shared;
+        let accounting = shared.lock().accounting;
+        loop {
+            let (id, quantum) = {
+                let mut core = shared.lock();
+                loop {
+                    if core.shutdown {
+                        return;
+                    }
+                    if let Some(next) = core.next() {
+                        break next;
+                    }
+                    core = shared.ready.wait(core).unwrap();
+                }
+            };
+
+            let mut future = inner
+                .futures
+                .lock()
+                .unwrap()
+                .remove(&id)
+                .expect("task without a future");
+            let mut slice = 0;
+            loop {
+                let (completed, elapsed) = poll_task(future.as_mut(), id, shared);
+                let cost = accounting.cost(elapsed);
+                slice += cost;
+                if completed {
+                    drop(future);
+                    let mut core = shared.lock();
+                    core.ran(id, cost, slice, quantum, true);
+                    if core.tasks.is_empty() {
+                        inner.idle.notify_all();
+                    }
+                    break;
+                }
+
+                // Put the future back before the task can be picked by another worker
+                inner.futures.lock().unwrap().insert(id, future);
+                let again = shared.lock().ran(id, cost, slice, quantum, false);
+                if !again {
+                    shared.ready.notify_one();
+                    break;
+                }
+                future = inner.futures.lock().unwrap().remove(&id).unwrap();
+            }
+        }
+    }
+}
+
+impl Drop for MultiExecutor {
+    // Stop the workers, abandoning the tasks that have not completed
+    fn drop(&mut self) {
+        if !self.workers.is_empty() {
+            self.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two queues with a one poll quantum, so every poll ends the slice
+    fn core(boost: u32, io_stay: bool) -> Core {
+        Core::new(
+            SchedulerConfig::new(boost, false, io_stay),
+            vec![QueueConfig::new(1, 2, false), QueueConfig::new(1, 4, false)],
+            Accounting::Polls,
+        )
+    }
+
+    // Poll the next task once. A runnable task wakes itself, any other waits on I/O.
+    fn poll(core: &mut Core, runnable: bool) -> u64 {
+        let (id, quantum) = core.next().expect("no task is queued");
+        if runnable {
+            core.wake(id);
+        }
+        assert!(!core.ran(id, 1, 1, quantum, false));
+        id
+    }
+
+    // Wakes itself and returns Pending the given number of times, like a task that yields
+    struct Yield(u32);
+
+    impl Future for Yield {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 == 0 {
+                return Poll::Ready(());
+            }
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn demotes_after_allotment() {
+        let mut core = core(0, false);
+        let id = core.add();
+
+        poll(&mut core, true);
+        assert_eq!(core.tasks[&id].level, 0);
+        poll(&mut core, true);
+        assert_eq!(core.tasks[&id].level, 1);
+        assert_eq!(core.queues[1], [id]);
+        assert_eq!(core.stats.demotions, 1);
+    }
+
+    #[test]
+    fn keeps_level_while_waiting() {
+        let mut core = core(0, true);
+        let id = core.add();
+
+        poll(&mut core, true);
+        // Uses up the allotment, but io_stay keeps a task waiting on I/O at its level
+        poll(&mut core, false);
+        assert_eq!(core.tasks[&id].state, TaskState::Idle);
+        assert!(core.next().is_none());
+
+        assert!(core.wake(id));
+        assert_eq!(core.next(), Some((id, 1)));
+        assert_eq!(core.tasks[&id].level, 0);
+        assert_eq!(core.stats.demotions, 0);
+    }
+
+    #[test]
+    fn boost_moves_every_task_to_the_top() {
+        let mut core = core(3, false);
+        let id = core.add();
+
+        poll(&mut core, true);
+        poll(&mut core, true);
+        assert_eq!(core.tasks[&id].level, 1);
+        poll(&mut core, true);
+        assert_eq!(core.tasks[&id].level, 0);
+        assert_eq!(core.tasks[&id].allotment, 2);
+        assert_eq!(core.queues[0], [id]);
+        assert_eq!(core.stats.boosts, 1);
+    }
+
+    #[test]
+    fn wake_while_running_requeues() {
+        let mut core = core(0, false);
+        let id = core.add();
+
+        // Already queued or running, so no worker needs to be notified
+        assert!(!core.wake(id));
+        let (picked, _) = core.next().unwrap();
+        assert!(!core.wake(picked));
+        assert_eq!(core.tasks[&id].state, TaskState::Running { woken: true });
+
+        core.ran(id, 1, 1, 1, false);
+        assert_eq!(core.tasks[&id].state, TaskState::Queued);
+        assert_eq!(core.queues[0], [id]);
+    }
+
+    #[test]
+    fn single_thread_completes_every_task() {
+        let mut executor = Executor::new(
+            SchedulerConfig::new(0, false, false),
+            vec![QueueConfig::new(1, 2, false), QueueConfig::new(2, 4, false)],
+            Accounting::Polls,
+        );
+        let handles = (0..5)
+            .map(|i| {
+                executor.spawn(async move {
+                    Yield(i).await;
+                    i
+                })
+            })
+            .collect::<Vec<_>>();
+        executor.run();
+
+        let outputs = handles.iter().map(|h| h.try_take()).collect::<Vec<_>>();
+        assert_eq!(outputs, [Some(0), Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(executor.stats().completed, 5);
+    }
+
+    #[test]
+    fn multi_thread_completes_every_task() {
+        let executor = MultiExecutor::new(
+            SchedulerConfig::new(10, false, false),
+            vec![QueueConfig::new(1, 2, false), QueueConfig::new(2, 4, false)],
+            Accounting::Polls,
+            4,
+        );
+        let handles = (0..20)
+            .map(|i| {
+                executor.spawn(async move {
+                    Yield(i % 7).await;
+                    i
+                })
+            })
+            .collect::<Vec<_>>();
+        let stats = executor.shutdown();
+
+        let total = handles.iter().filter_map(|h| h.try_take()).sum::<u32>();
+        assert_eq!(total, (0..20).sum());
+        assert_eq!(stats.completed, 20);
+        // One poll per yield and one to complete
+        assert_eq!(stats.polls, (0..20).map(|i| i % 7 + 1).sum::<u64>());
+    }
+}
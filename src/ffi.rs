@@ -0,0 +1,282 @@
+// ffi.rs
+// C ABI over the simulator, for harnesses written in C or driven through Python's ctypes.
+// The handle owns a Scheduler, and events are read back from its slices and finished jobs.
+// include/mlfq.h declares the same types and functions, and tests/ffi_header.rs checks them.
+// Author: Hank Bao
+
+use std::ptr;
+
+use crate::config::{JobConfig, QueueConfig, SchedulerConfig};
+use crate::metrics::Metrics;
+use crate::scheduler::Scheduler;
+
+pub const MLFQ_EVENT_RUN: u32 = 0;
+pub const MLFQ_EVENT_FINISH: u32 = 1;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MlfqQueueConfig {
+    pub quantum: u32,
+    pub allotment: u32,
+    pub push_front: bool,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct MlfqMetrics {
+    pub idle_time: u32,
+    pub average_turnaround_time: u32,
+    pub average_response_time: u32,
+    pub makespan: u32,
+    pub p99_turnaround_time: u32,
+    pub max_wait_time: u32,
+    pub deadline_misses: u32,
+}
+
+// A process ran from time for length ticks, or finished at time with length 0
+#[repr(C)]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct MlfqEvent {
+    pub kind: u32,
+    pub pid: u32,
+    pub time: u32,
+    pub length: u32,
+}
+
+// Opaque to C
+pub struct MlfqScheduler {
+    scheduler: Scheduler,
+    jobs: u32,
+    // Events already returned by mlfq_next_event
+    next_slice: usize,
+    next_finish: usize,
+}
+
+/// Create a scheduler from count queue configs, the highest priority first.
+/// Returns NULL if a config is invalid. Free it with mlfq_destroy.
+///
+/// # Safety
+/// `queues` must point to `count` readable configs.
+#[no_mangle]
+pub unsafe extern "C" fn mlfq_create(
+    queues: *const MlfqQueueConfig,
+    count: usize,
+    boost_interval: u32,
+    io_bump: bool,
+    io_stay: bool,
+) -> *mut MlfqScheduler {
+    if queues.is_null() || count == 0 {
+        return ptr::null_mut();
+    }
+
+    let queues = std::slice::from_raw_parts(queues, count);
+    if queues.iter().any(|q| q.quantum == 0 || q.allotment == 0) {
+        return ptr::null_mut();
+    }
+
+    let queue_configs = queues
+        .iter()
+        .map(|q| QueueConfig::new(q.quantum, q.allotment, q.push_front))
+        .collect();
+    let config = SchedulerConfig::new(boost_interval, io_bump, io_stay);
+    let mut scheduler = Scheduler::new(config, queue_configs);
+    scheduler.set_trace(false);
+
+    Box::into_raw(Box::new(MlfqScheduler {
+        scheduler,
+        jobs: 0,
+        next_slice: 0,
+        next_finish: 0,
+    }))
+}
+
+/// Free a scheduler and everything it holds.
+///
+/// # Safety
+/// `handle` must come from mlfq_create and not be used afterwards. NULL is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn mlfq_destroy(handle: *mut MlfqScheduler) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Add a job, which gets the next pid starting at 0. Returns the pid, or -1 if the job is invalid.
+///
+/// # Safety
+/// `handle` must come from mlfq_create.
+#[no_mangle]
+pub unsafe extern "C" fn mlfq_add_job(
+    handle: *mut MlfqScheduler,
+    arrival_time: u32,
+    workload: u32,
+    io_interval: u32,
+    io_length: u32,
+) -> i64 {
+    let handle = match handle.as_mut() {
+        Some(handle) => handle,
+        None => return -1,
+    };
+    if workload == 0 {
+        return -1;
+    }
+
+    let job = JobConfig::new(arrival_time, workload, io_interval, io_length);
+    handle.scheduler.add_job(job);
+    handle.jobs += 1;
+    (handle.jobs - 1) as i64
+}
+
+/// Run one scheduling decision. Returns 1 if jobs are left, 0 when all have finished, -1 on error.
+///
+/// # Safety
+/// `handle` must come from mlfq_create.
+#[no_mangle]
+pub unsafe extern "C" fn mlfq_step(handle: *mut MlfqScheduler) -> i32 {
+    let handle = match handle.as_mut() {
+        Some(handle) => handle,
+        None => return -1,
+    };
+
+    if !handle.scheduler.is_finished() {
+        handle.scheduler.run_tick();
+    }
+    !handle.scheduler.is_finished() as i32
+}
+
+/// Returns 0 once every job has finished, -1 on error.
+///
+/// # Safety
+/// `handle` must come from mlfq_create.
+#[no_mangle]
+pub unsafe extern "C" fn mlfq_run(handle: *mut MlfqScheduler) -> i32 {
+    match handle.as_mut() {
+        Some(handle) => {
+            handle.scheduler.run_to_completion();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// The simulated time, 0 on error.
+///
+/// # Safety
+/// `handle` must come from mlfq_create.
+#[no_mangle]
+pub unsafe extern "C" fn mlfq_current_time(handle: *const MlfqScheduler) -> u32 {
+    handle.as_ref().map_or(0, |h| h.scheduler.current_time())
+}
+
+/// Fill in the metrics of the jobs finished so far. Returns -1 if no job was added.
+///
+/// # Safety
+/// `handle` must come from mlfq_create, and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn mlfq_metrics(handle: *const MlfqScheduler, out: *mut MlfqMetrics) -> i32 {
+    let (handle, out) = match (handle.as_ref(), out.as_mut()) {
+        (Some(handle), Some(out)) if handle.jobs > 0 => (handle, out),
+        _ => return -1,
+    };
+
+    // The scheduler's averages count every admitted job, so mid-run they are taken here
+    // over the jobs that have finished
+    let finished = handle.scheduler.finished_processes();
+    let count = (finished.len() as u32).max(1);
+    let metrics = Metrics::from(&handle.scheduler);
+    *out = MlfqMetrics {
+        idle_time: metrics.idle_time(),
+        average_turnaround_time: finished.iter().map(|p| p.turnaround_time()).sum::<u32>() / count,
+        average_response_time: finished.iter().map(|p| p.response_time()).sum::<u32>() / count,
+        makespan: metrics.makespan(),
+        p99_turnaround_time: metrics.p99_turnaround_time(),
+        max_wait_time: metrics.max_wait_time(),
+        deadline_misses: metrics.deadline_misses(),
+    };
+    0
+}
+
+/// Take the next event in time order. Returns 1 if one was written, 0 if there is none yet.
+///
+/// # Safety
+/// `handle` must come from mlfq_create, and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn mlfq_next_event(handle: *mut MlfqScheduler, out: *mut MlfqEvent) -> i32 {
+    let (handle, out) = match (handle.as_mut(), out.as_mut()) {
+        (Some(handle), Some(out)) => (handle, out),
+        _ => return -1,
+    };
+
+    let slice = handle
+        .scheduler
+        .slices()
+        .get(handle.next_slice)
+        .map(|s| MlfqEvent {
+            kind: MLFQ_EVENT_RUN,
+            pid: s.pid(),
+            time: s.start(),
+            length: s.length(),
+        });
+    let finish = handle
+        .scheduler
+        .finished_processes()
+        .get(handle.next_finish)
+        .map(|p| MlfqEvent {
+            kind: MLFQ_EVENT_FINISH,
+            pid: p.pid(),
+            time: p.start_time() + p.turnaround_time(),
+            length: 0,
+        });
+
+    // A job finishes at the end of its last slice, so a slice starting then comes after it
+    let event = match (slice, finish) {
+        (Some(s), Some(f)) if f.time <= s.time => {
+            handle.next_finish += 1;
+            f
+        }
+        (Some(s), _) => {
+            handle.next_slice += 1;
+            s
+        }
+        (None, Some(f)) => {
+            handle.next_finish += 1;
+            f
+        }
+        (None, None) => return 0,
+    };
+
+    *out = event;
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_average_finished_jobs() {
+        let queues = [MlfqQueueConfig {
+            quantum: 10,
+            allotment: 1000,
+            push_front: false,
+        }];
+        unsafe {
+            let s = mlfq_create(queues.as_ptr(), queues.len(), 0, false, false);
+            mlfq_add_job(s, 0, 10, 0, 0);
+            mlfq_add_job(s, 0, 100, 0, 0);
+            mlfq_step(s);
+
+            // Only job 0 has finished, after 10 ticks
+            let mut metrics = MlfqMetrics::default();
+            assert_eq!(mlfq_metrics(s, &mut metrics), 0);
+            assert_eq!(metrics.average_turnaround_time, 10);
+            assert_eq!(metrics.average_response_time, 0);
+
+            mlfq_run(s);
+            assert_eq!(mlfq_metrics(s, &mut metrics), 0);
+            assert_eq!(metrics.average_turnaround_time, 60);
+            assert_eq!(metrics.average_response_time, 5);
+            mlfq_destroy(s);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// lib.rs
+// Library root of the MLFQ scheduler, shared by the command line tool and embedding tools.
+// Author: Hank Bao
+
+pub mod compare;
+pub mod config;
+pub mod dag;
+pub mod dispatch;
+pub mod events;
+pub mod executor;
+pub mod ffi;
+pub mod metrics;
+pub mod optimize;
+pub mod periodic;
+pub mod policy;
+pub mod process;
+pub mod queue;
+pub mod realtime;
+pub mod rng;
+pub mod scheduler;
+pub mod snapshot;
+#[cfg(target_os = "linux")]
+pub mod spool;
+#[cfg(target_os = "linux")]
+pub mod supervise;
+pub mod sweep;
+pub mod tree;
+pub mod whatif;
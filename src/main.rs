@@ -2,31 +2,287 @@
 // main entry point for the MLFQ scheduler.
 // Author: Hank Bao
 
-mod config;
-mod process;
-mod queue;
-mod scheduler;
-
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 use std::process::ExitCode;
+use std::time::Duration;
 
-use config::{JobConfig, QueueConfig, SchedulerConfig};
-use scheduler::Scheduler;
+use mlfq::compare::{self, NamedConfig};
+use mlfq::config::{
+    ChildPlacement, Discipline, JobConfig, QueueConfig, SchedClass, SchedulerConfig, Spawn,
+};
+use mlfq::dag;
+use mlfq::dispatch::DispatchTable;
+use mlfq::events::Event;
+use mlfq::optimize::{Bounds, Goal, Method, Optimizer, Search, Target};
+use mlfq::periodic;
+use mlfq::policy::{PolicyKind, PolicyOptions};
+use mlfq::scheduler::Scheduler;
+use mlfq::snapshot;
+#[cfg(target_os = "linux")]
+use mlfq::spool::{self, Spooler};
+#[cfg(target_os = "linux")]
+use mlfq::supervise::Supervisor;
+use mlfq::sweep::{self, Objective, SweepSpace};
+use mlfq::tree;
+use mlfq::whatif;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-#[command(arg_required_else_help = true)]
+#[command(arg_required_else_help = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Length of time slice per queue level, specified as x,y,z,... where x is the quantum length for the highest priority queue, y the next highest, and so forth
-    #[arg(short, long, value_name = "QUANTUMS")]
-    quantum_list: String,
+    #[arg(
+        short,
+        long,
+        value_name = "QUANTUMS",
+        required_unless_present_any = ["ts_table", "policy", "resume"]
+    )]
+    quantum_list: Option<String>,
     /// Length of time allotment per queue level, specified as x,y,z,... where x is the # of time slices for the highest priority queue, y the next highest, and so forth
-    #[arg(short, long, value_name = "ALLOTMENTS")]
+    #[arg(
+        short,
+        long,
+        value_name = "ALLOTMENTS",
+        required_unless_present_any = ["ts_table", "policy", "resume"]
+    )]
+    allotment_list: Option<String>,
+    /// A comma-separated list of jobs to run, in the form x1,y1,z1,u1:x2,y2,z2,u2:... where x is start time, y is run time, and z is how often the job issues an I/O request, and u is how long the I/O request lasts. Optional attributes may follow as key=value: nice=n, tickets=n, currency=NAME, transfer=PID to lend the tickets to job PID while blocked on I/O, class=fifo|rr|idle|other and rtprio=n from 1 to 99 for the real-time classes, period=n to release the job every n ticks with the run time as its WCET, and deadline=n relative to each release, after=PID+PID+... to admit the job only after those jobs finish, and spawn=AT/WORK or spawn=AT/WORK/IO_INTERVAL/IO_LENGTH to spawn a child job once the job has done AT ticks of work
+    #[arg(short, long, value_name = "JOBS", required_unless_present = "resume")]
+    job_list: Option<String>,
+    /// How often to boost the priority of all jobs back to high priority
+    #[arg(short, long, value_name = "BOOST", default_value = "0")]
+    boost: u32,
+    /// If specified, jobs that finished I/O move immediately to front of current queue
+    #[arg(short, long, default_value = "false")]
+    io_bump: bool,
+    /// Reset and stay at same priority level when issuing I/O
+    #[arg(short, long, default_value = "false")]
+    stay: bool,
+    /// Annotate every scheduling decision with the MLFQ rule that caused it
+    #[arg(short, long, default_value = "false")]
+    explain: bool,
+    /// Drive the queues by a Solaris time-sharing dispatch table instead of quantums and allotments, either "default" for the Solaris default table or a file in the format of `dispadmin -c TS -g`
+    #[arg(long, value_name = "TABLE", conflicts_with_all = ["quantum_list", "allotment_list"])]
+    ts_table: Option<String>,
+    /// Ticks between two aging passes of the dispatch table
+    #[arg(
+        long,
+        value_name = "TICKS",
+        default_value = "1000",
+        requires = "ts_table"
+    )]
+    ts_update: u32,
+    /// Per-level aging thresholds, specified as x,y,z,... A job that waits this many ticks at a level without running is promoted on its own. 0 disables aging at a level, and the highest priority queue never ages. Use with or instead of --boost
+    #[arg(long, value_name = "THRESHOLDS", conflicts_with = "ts_table")]
+    aging: Option<String>,
+    /// Per-level targets of aging promotions, specified as x,y,z,... where '-' promotes one level up
+    #[arg(
+        long,
+        value_name = "TARGETS",
+        requires = "aging",
+        allow_hyphen_values = true
+    )]
+    aging_target: Option<String>,
+    /// Per-level scheduling discipline among the ready jobs of a queue, specified as x,y,z,... where each is rr (round-robin), fcfs (run until it blocks or finishes), srt (shortest remaining time) or lottery (weighted by the job's tickets)
+    #[arg(long, value_name = "DISCIPLINES", conflicts_with = "ts_table")]
+    discipline: Option<String>,
+    /// Schedule with another policy instead of the MLFQ rules. Its priorities are mapped onto queues, so traces and metrics stay comparable
+    #[arg(short, long, value_enum, conflicts_with_all = ["quantum_list", "allotment_list", "ts_table", "aging", "discipline"])]
+    policy: Option<PolicyKind>,
+    /// Interactivity score below which the ULE policy treats a job as interactive, from 0 to 100
+    #[arg(long, value_name = "SCORE", default_value = "30")]
+    interact_threshold: u32,
+    /// Limit real-time jobs to this many ticks in every --rt-period, like sched_rt_runtime_us
+    #[arg(long, value_name = "TICKS")]
+    rt_runtime: Option<u32>,
+    /// Length of the real-time throttling period
+    #[arg(
+        long,
+        value_name = "TICKS",
+        default_value = "1000",
+        requires = "rt_runtime"
+    )]
+    rt_period: u32,
+    /// Release periodic jobs until this time, the hyperperiod of their periods by default
+    #[arg(long, value_name = "TICKS")]
+    horizon: Option<u32>,
+    /// Seed for the draws of the lottery policy and of lottery queues
+    #[arg(long, default_value = "0")]
+    seed: u64,
+    /// Currencies of the lottery and stride policies, in the form NAME=TICKETS,... where TICKETS is the number of base tickets funding the currency
+    #[arg(long, value_name = "CURRENCIES")]
+    currencies: Option<String>,
+    /// Save the full scheduler state to this file during the run, then continue
+    #[arg(long, value_name = "FILE")]
+    snapshot: Option<String>,
+    /// Take the snapshot at the first scheduling decision at or after this time, 0 for before the first one
+    #[arg(long, value_name = "TIME", default_value = "0", requires = "snapshot")]
+    snapshot_at: u32,
+    /// Where children spawned with spawn= enter the queues: top for queue 0, or inherit for the level of the parent
+    #[arg(long, value_enum, default_value = "top")]
+    child_placement: ChildPlacement,
+    /// Events to inject during the run, in the form t1,ACTION,ARGS:t2,ACTION,ARGS:... where ACTION is kill,PID, suspend,PID, resume,PID, move,PID,LEVEL, quantum,LEVEL,TICKS, boost-interval,TICKS or boost
+    #[arg(long, value_name = "EVENTS")]
+    events: Option<String>,
+    /// Continue the run saved in a snapshot file. Queues, jobs and settings all come from the snapshot
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["quantum_list", "allotment_list", "job_list", "ts_table", "aging", "discipline", "policy", "rt_runtime"])]
+    resume: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the same workload under every combination of the given settings
+    Sweep(SweepArgs),
+    /// Search for the configuration that minimizes a target metric on a workload
+    Optimize(OptimizeArgs),
+    /// Run one workload through several named configurations and compare the results
+    Compare(CompareArgs),
+    /// Run a workload to some time, fork it there and compare the original with a changed copy
+    Whatif(WhatifArgs),
+    /// Launch shell commands as child processes and schedule them by the MLFQ rules with SIGSTOP/SIGCONT (Linux only)
+    Supervise(SuperviseArgs),
+    /// Queue shell commands with a daemon that runs them by the MLFQ rules, like task-spooler (Linux only)
+    Spool(SpoolArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct SweepArgs {
+    /// Candidate quantum lengths per queue level, separated by ','. Each level takes alternatives separated by '|', where each alternative is x, lo-hi or lo-hi/step
+    #[arg(short, long, value_name = "QUANTUM_SPECS")]
+    quantum_list: String,
+    /// Candidate allotments per queue level, in the same form as the quantum specs
+    #[arg(short, long, value_name = "ALLOTMENT_SPECS")]
     allotment_list: String,
-    /// A comma-separated list of jobs to run, in the form x1,y1,z1,u1:x2,y2,z2,u2:... where x is start time, y is run time, and z is how often the job issues an I/O request, and u is how long the I/O request lasts
+    /// The job list to run, in the same form as the top-level job list
     #[arg(short, long, value_name = "JOBS")]
     job_list: String,
-    /// How often to boost the priority of all jobs back to high priority
+    /// Candidate priority boost intervals, in the form of a single value spec
+    #[arg(short, long, value_name = "BOOST_SPEC", default_value = "0")]
+    boost: String,
+    /// Whether jobs that finished I/O move to the front of the queue: true, false or both
+    #[arg(short, long, value_name = "BOOL_SPEC", default_value = "false")]
+    io_bump: String,
+    /// Whether jobs reset and stay at the same level when issuing I/O: true, false or both
+    #[arg(short, long, value_name = "BOOL_SPEC", default_value = "false")]
+    stay: String,
+    /// Whether new jobs are added to the front of every queue: true, false or both
+    #[arg(long, value_name = "BOOL_SPEC", default_value = "true")]
+    push_front: String,
+    /// The metric to sort the results by, lowest first
+    #[arg(long, value_enum, default_value = "turnaround")]
+    sort_by: Objective,
+    /// Number of worker threads, 0 for one per available CPU
+    #[arg(short, long, default_value = "0")]
+    threads: usize,
+    /// Only print the best N combinations, 0 for all
+    #[arg(long, default_value = "0")]
+    top: usize,
+}
+
+#[derive(clap::Args, Debug)]
+struct OptimizeArgs {
+    /// The job list to run, in the same form as the top-level job list
+    #[arg(short, long, value_name = "JOBS")]
+    job_list: String,
+    /// The metric to minimize
+    #[arg(long, value_enum, default_value = "mean-response")]
+    target: Target,
+    /// Weight of the mean response time in the weighted target, the rest goes to p99 turnaround time
+    #[arg(long, default_value = "0.5")]
+    weight: f64,
+    /// Reject configurations where any job waits longer than this while schedulable
+    #[arg(long, value_name = "TICKS")]
+    starvation_cap: Option<u32>,
+    /// The search method
+    #[arg(short, long, value_enum, default_value = "anneal")]
+    method: Method,
+    /// Number of search iterations
+    #[arg(long, default_value = "500")]
+    iterations: u32,
+    /// Seed for the random number generator
+    #[arg(long, default_value = "0")]
+    seed: u64,
+    /// Initial temperature for simulated annealing, in units of cost
+    #[arg(long, default_value = "10.0")]
+    temperature: f64,
+    /// The maximum number of queue levels
+    #[arg(long, default_value = "5")]
+    max_queues: usize,
+    /// The range of quantum lengths to search, in the form lo-hi
+    #[arg(long, value_name = "RANGE", default_value = "1-100")]
+    quantum_range: String,
+    /// The range of allotments to search, in the form lo-hi
+    #[arg(long, value_name = "RANGE", default_value = "1-200")]
+    allotment_range: String,
+    /// The range of priority boost intervals to search, in the form lo-hi, where 0 disables boosting
+    #[arg(long, value_name = "RANGE", default_value = "0-500")]
+    boost_range: String,
+    /// If specified, jobs that finished I/O move immediately to front of current queue
+    #[arg(short, long, default_value = "false")]
+    io_bump: bool,
+    /// Reset and stay at same priority level when issuing I/O
+    #[arg(short, long, default_value = "false")]
+    stay: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct CompareArgs {
+    /// The job list to run, in the same form as the top-level job list
+    #[arg(short, long, value_name = "JOBS")]
+    job_list: String,
+    /// Release periodic jobs until this time, the hyperperiod of their periods by default
+    #[arg(long, value_name = "TICKS")]
+    horizon: Option<u32>,
+    /// A config to compare, either a preset (ostep-default, solaris-ts, base, no-boost, io-bump, io-stay), the name of a policy, or NAME=SPEC, where SPEC overrides the base with q=x,y,z a=x,y,z b=n front=true|false bump stay
+    #[arg(short, long = "config", value_name = "CONFIG")]
+    configs: Vec<String>,
+    /// A file with one config per line, in the same form as --config. Lines starting with '#' are ignored
+    #[arg(short = 'f', long, value_name = "FILE")]
+    config_file: Option<String>,
+    /// The name of the config to compute deltas against, the first config by default
+    #[arg(long, value_name = "NAME")]
+    baseline: Option<String>,
+    /// Quantums of the base config
+    #[arg(short, long, value_name = "QUANTUMS", default_value = "10,10,10")]
+    quantum_list: String,
+    /// Allotments of the base config
+    #[arg(short, long, value_name = "ALLOTMENTS", default_value = "10,10,10")]
+    allotment_list: String,
+    /// Priority boost interval of the base config
+    #[arg(short, long, value_name = "BOOST", default_value = "0")]
+    boost: u32,
+    /// Enable I/O bump in the base config
+    #[arg(short, long, default_value = "false")]
+    io_bump: bool,
+    /// Enable I/O stay in the base config
+    #[arg(short, long, default_value = "false")]
+    stay: bool,
+    /// Also print aligned Gantt timelines of every config
+    #[arg(short, long, default_value = "false")]
+    gantt: bool,
+    /// Width of the Gantt timelines in columns
+    #[arg(long, default_value = "100")]
+    gantt_width: usize,
+}
+
+#[derive(clap::Args, Debug)]
+struct WhatifArgs {
+    /// The job list to run, in the same form as the top-level job list
+    #[arg(short, long, value_name = "JOBS", required_unless_present = "resume")]
+    job_list: Option<String>,
+    /// Release periodic jobs until this time, the hyperperiod of their periods by default
+    #[arg(long, value_name = "TICKS")]
+    horizon: Option<u32>,
+    /// Quantum lengths per queue level
+    #[arg(short, long, value_name = "QUANTUMS", default_value = "10,10,10")]
+    quantum_list: String,
+    /// Allotments per queue level
+    #[arg(short, long, value_name = "ALLOTMENTS", default_value = "10,10,10")]
+    allotment_list: String,
+    /// Priority boost interval
     #[arg(short, long, value_name = "BOOST", default_value = "0")]
     boost: u32,
     /// If specified, jobs that finished I/O move immediately to front of current queue
@@ -35,55 +291,784 @@ struct Args {
     /// Reset and stay at same priority level when issuing I/O
     #[arg(short, long, default_value = "false")]
     stay: bool,
+    /// Start from a snapshot written with --snapshot instead of the job list and queues
+    #[arg(long, value_name = "FILE", conflicts_with = "job_list")]
+    resume: Option<String>,
+    /// The time to fork the run at
+    #[arg(long, value_name = "TIME")]
+    at: u32,
+    /// A setting of the changed copy: boost=n, bump=true|false, stay=true|false, front=true|false, q=x,y,z, a=x,y,z, aging=x,y,z or discipline=x,y,z
+    #[arg(long = "set", value_name = "KEY=VALUE", required = true)]
+    changes: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct SuperviseArgs {
+    /// Quantum lengths per queue level in milliseconds, specified as x,y,z,...
+    #[arg(short, long, value_name = "QUANTUMS", default_value = "20,40,80")]
+    quantum_list: String,
+    /// Allotments of CPU time per queue level in milliseconds, specified as x,y,z,...
+    #[arg(short, long, value_name = "ALLOTMENTS", default_value = "40,80,160")]
+    allotment_list: String,
+    /// How often to boost all commands back to the highest queue, in milliseconds
+    #[arg(short, long, value_name = "BOOST", default_value = "0")]
+    boost: u32,
+    /// Commands returning from I/O move to the front of their queue
+    #[arg(short, long, default_value = "false")]
+    io_bump: bool,
+    /// Reset and stay at the same level when blocking on I/O
+    #[arg(short, long, default_value = "false")]
+    stay: bool,
+    /// Milliseconds between two checks of the running command
+    #[arg(long, default_value = "1")]
+    poll: u64,
+    /// Do not print every scheduling event
+    #[arg(long, default_value = "false")]
+    quiet: bool,
+    /// The shell commands to run
+    #[arg(required = true, value_name = "COMMAND")]
+    commands: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct SpoolArgs {
+    /// Path of the daemon's Unix socket, mlfq-spool.sock in the temporary directory by default
+    #[arg(long, global = true, value_name = "PATH")]
+    socket: Option<PathBuf>,
+    #[command(subcommand)]
+    action: SpoolAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum SpoolAction {
+    /// Run the daemon in the foreground
+    Daemon(DaemonArgs),
+    /// Queue a shell command, which enters the highest queue
+    Submit {
+        /// The shell command to run
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// List every job with its state, level and CPU time
+    List,
+    /// Kill a queued or running job
+    Kill {
+        /// The id of the job
+        id: u32,
+    },
+    /// Move a job to another queue level, where 0 is the highest priority
+    Renice {
+        /// The id of the job
+        id: u32,
+        /// The new queue level
+        level: usize,
+    },
+    /// Show job counts and CPU time
+    Stats,
+    /// Stop the daemon. Unfinished jobs are killed, and start again when the daemon restarts
+    Shutdown,
+}
+
+#[derive(clap::Args, Debug)]
+struct DaemonArgs {
+    /// Quantum lengths per queue level in milliseconds, specified as x,y,z,...
+    #[arg(short, long, value_name = "QUANTUMS", default_value = "100,200,400")]
+    quantum_list: String,
+    /// Allotments of CPU time per queue level in milliseconds, specified as x,y,z,...
+    #[arg(short, long, value_name = "ALLOTMENTS", default_value = "200,400,800")]
+    allotment_list: String,
+    /// How often to boost all jobs back to the highest queue, in milliseconds
+    #[arg(short, long, value_name = "BOOST", default_value = "10000")]
+    boost: u32,
+    /// Jobs returning from I/O move to the front of their queue
+    #[arg(short, long, default_value = "false")]
+    io_bump: bool,
+    /// Reset and stay at the same level when blocking on I/O
+    #[arg(short, long, default_value = "false")]
+    stay: bool,
+    /// How many jobs may run at the same time
+    #[arg(long, default_value = "1")]
+    slots: usize,
+    /// File the job list is saved to, mlfq-spool.state in the temporary directory by default
+    #[arg(long, value_name = "PATH")]
+    state: Option<PathBuf>,
+    /// Milliseconds between two rounds of the daemon
+    #[arg(long, default_value = "10")]
+    poll: u64,
+    /// Print every scheduling event
+    #[arg(short, long, default_value = "false")]
+    verbose: bool,
 }
 
 fn main() -> ExitCode {
     match Args::try_parse() {
-        Ok(args) => {
-            let scheduler_config = match parse_scheduler_config(args.boost, args.io_bump, args.stay)
-            {
-                Some(config) => config,
+        Ok(args) => match args.command {
+            Some(Command::Sweep(sweep_args)) => run_sweep(sweep_args),
+            Some(Command::Optimize(optimize_args)) => run_optimize(optimize_args),
+            Some(Command::Compare(compare_args)) => run_compare(compare_args),
+            Some(Command::Whatif(whatif_args)) => run_whatif(whatif_args),
+            Some(Command::Supervise(supervise_args)) => run_supervise(supervise_args),
+            Some(Command::Spool(spool_args)) => run_spool(spool_args),
+            None => run_simulation(args),
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_simulation(args: Args) -> ExitCode {
+    let snapshot_to = args.snapshot.clone().map(|path| (path, args.snapshot_at));
+
+    let events = match args.events {
+        Some(events) => match parse_events(events) {
+            Some(events) => events,
+            None => return ExitCode::FAILURE,
+        },
+        None => Vec::new(),
+    };
+
+    if let Some(path) = args.resume.as_deref() {
+        let mut scheduler = match snapshot::load(path) {
+            Some(scheduler) => scheduler,
+            None => return ExitCode::FAILURE,
+        };
+        println!("Resumed at {} from {}.", scheduler.current_time(), path);
+        scheduler.add_events(events);
+        return finish_simulation(scheduler, snapshot_to);
+    }
+
+    let scheduler_config = match parse_scheduler_config(args.boost, args.io_bump, args.stay) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+
+    // clap guarantees the job list is present when no subcommand is given
+    let job_list = args.job_list.unwrap_or_default();
+
+    let task_configs = match parse_job_configs(job_list) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+    let (job_configs, tasks) = release_periodic(&task_configs, args.horizon);
+
+    let mut scheduler = match (args.policy, args.ts_table) {
+        (Some(kind), _) => {
+            if args.interact_threshold > 100 {
+                eprintln!("interact_threshold must be between 0 and 100.");
+                return ExitCode::FAILURE;
+            }
+
+            let currencies = match parse_currencies(args.currencies.as_deref(), &job_configs) {
+                Some(currencies) => currencies,
                 None => return ExitCode::FAILURE,
             };
 
-            let queue_config = match parse_queue_configs(args.quantum_list, args.allotment_list) {
-                Some(config) => config,
+            let mut options = PolicyOptions::default();
+            options.set_interact_threshold(args.interact_threshold);
+            options.set_seed(args.seed);
+            options.set_currencies(currencies);
+
+            Scheduler::with_policy(scheduler_config, kind.create(&options))
+        }
+        (None, Some(table)) => {
+            let table = match parse_dispatch_table(&table, args.ts_update) {
+                Some(table) => table,
                 None => return ExitCode::FAILURE,
             };
+            Scheduler::with_dispatch_table(scheduler_config, table)
+        }
+        (None, None) => {
+            // clap guarantees these are present when no dispatch table is given
+            let quantum_list = args.quantum_list.unwrap_or_default();
+            let allotment_list = args.allotment_list.unwrap_or_default();
 
-            let job_configs = match parse_job_configs(args.job_list) {
+            let mut queue_config = match parse_queue_configs(quantum_list, allotment_list) {
                 Some(config) => config,
                 None => return ExitCode::FAILURE,
             };
 
-            let mut scheduler = Scheduler::new(scheduler_config, queue_config);
-            scheduler.add_jobs(job_configs);
+            if let Some(aging) = args.aging {
+                if !parse_aging(&aging, args.aging_target.as_deref(), &mut queue_config) {
+                    return ExitCode::FAILURE;
+                }
+            }
 
-            // let the scheduler ticks
-            while !scheduler.is_finished() {
-                scheduler.run_tick();
+            if let Some(discipline) = args.discipline {
+                if !parse_disciplines(&discipline, &mut queue_config) {
+                    return ExitCode::FAILURE;
+                }
             }
 
-            println!("All processes finished.");
-            println!("Total idle time: {}.", scheduler.total_idle_time());
-            println!(
-                "Average turnaround time: {}.",
-                scheduler.average_turnaround_time()
-            );
+            Scheduler::new(scheduler_config, queue_config)
+        }
+    };
+    if let Some(runtime) = args.rt_runtime {
+        if runtime == 0 || runtime > args.rt_period {
+            eprintln!("rt_runtime must be between 1 and rt_period.");
+            return ExitCode::FAILURE;
+        }
+        scheduler.set_rt_throttle(runtime, args.rt_period);
+    }
+    scheduler.set_explain(args.explain);
+    scheduler.set_seed(args.seed);
+    scheduler.set_child_placement(args.child_placement);
+    scheduler.set_tasks(task_configs, tasks);
+    scheduler.add_jobs(job_configs);
+    scheduler.add_events(events);
+
+    finish_simulation(scheduler, snapshot_to)
+}
+
+// Run to completion, taking a snapshot on the way if asked, and print the results
+fn finish_simulation(mut scheduler: Scheduler, snapshot_to: Option<(String, u32)>) -> ExitCode {
+    if let Some((path, at)) = snapshot_to {
+        while !scheduler.is_finished() && scheduler.current_time() < at {
+            scheduler.run_tick();
+        }
+        if !snapshot::save(&scheduler, &path) {
+            return ExitCode::FAILURE;
+        }
+        println!(
+            "Snapshot taken at {} and saved to {}.",
+            scheduler.current_time(),
+            path
+        );
+    }
+
+    // let the scheduler ticks
+    scheduler.run_to_completion();
+
+    // Jobs that wait for a killed or suspended job are never released
+    if scheduler.waiting_jobs().is_empty() {
+        println!("All processes finished.");
+    } else {
+        println!("All released processes finished.");
+    }
+    if !scheduler.killed_processes().is_empty() {
+        let pids = scheduler
+            .killed_processes()
+            .iter()
+            .map(|p| p.pid().to_string())
+            .collect::<Vec<String>>();
+        println!("Killed processes: {}.", pids.join(", "));
+    }
+    if !scheduler.waiting_jobs().is_empty() {
+        let pids = scheduler
+            .waiting_jobs()
+            .iter()
+            .map(|(pid, _)| pid.to_string())
+            .collect::<Vec<String>>();
+        println!("Processes never released: {}.", pids.join(", "));
+    }
+    let suspended = scheduler
+        .suspended_processes()
+        .map(|p| p.pid().to_string())
+        .collect::<Vec<String>>();
+    if !suspended.is_empty() {
+        println!("Processes never resumed: {}.", suspended.join(", "));
+    }
+    println!("Total idle time: {}.", scheduler.total_idle_time());
+    println!(
+        "Average turnaround time: {}.",
+        scheduler.average_turnaround_time()
+    );
+    println!(
+        "Average response time: {}.",
+        scheduler.average_response_time()
+    );
+
+    if let Some(policy) = scheduler.policy() {
+        policy.report();
+    }
+
+    let task_configs = scheduler.task_configs();
+    if task_configs
+        .iter()
+        .any(|j| j.period() > 0 || j.deadline().is_some())
+    {
+        periodic::print_report(
+            task_configs,
+            scheduler.tasks(),
+            scheduler.finished_processes(),
+        );
+    }
+
+    if !scheduler.children().is_empty() {
+        tree::print_report(&scheduler);
+    }
+
+    if task_configs.iter().any(|j| !j.dependencies().is_empty()) {
+        dag::print_report(task_configs, scheduler.current_time());
+    }
+
+    if scheduler.realtime().throttled_count() > 0 {
+        println!(
+            "Real-time jobs throttled {} times.",
+            scheduler.realtime().throttled_count()
+        );
+    }
+
+    if scheduler.queues().iter().any(|q| q.aging_threshold() > 0) {
+        let mut finished = scheduler.finished_processes().iter().collect::<Vec<_>>();
+        finished.sort_by_key(|p| p.pid());
+        for p in finished {
             println!(
-                "Average response time: {}.",
-                scheduler.average_response_time()
+                "Process {} promoted {} times by aging.",
+                p.pid(),
+                p.promotions()
             );
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn run_sweep(args: SweepArgs) -> ExitCode {
+    let quantums = match sweep::parse_level_specs(&args.quantum_list) {
+        Some(specs) => specs,
+        None => {
+            eprintln!("Invalid value spec found in quantum_list.");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let allotments = match sweep::parse_level_specs(&args.allotment_list) {
+        Some(specs) => specs,
+        None => {
+            eprintln!("Invalid value spec found in allotment_list.");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if quantums.len() != allotments.len() {
+        eprintln!("quantum_list and allotment_list must have the same length");
+        return ExitCode::FAILURE;
+    }
+
+    let boosts = match sweep::parse_value_spec(&args.boost) {
+        Some(values) => values,
+        None => {
+            eprintln!("Invalid value spec found in boost.");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (io_bumps, io_stays, push_fronts) = match (
+        sweep::parse_bool_spec(&args.io_bump),
+        sweep::parse_bool_spec(&args.stay),
+        sweep::parse_bool_spec(&args.push_front),
+    ) {
+        (Some(io_bumps), Some(io_stays), Some(push_fronts)) => (io_bumps, io_stays, push_fronts),
+        _ => {
+            eprintln!("Boolean specs must be one of true, false or both.");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let job_configs = match parse_job_configs(args.job_list) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+    let (job_configs, _) = release_periodic(&job_configs, None);
+
+    let space = SweepSpace::new(
+        quantums,
+        allotments,
+        push_fronts,
+        boosts,
+        io_bumps,
+        io_stays,
+    );
+    let total = match space.size() {
+        Some(total) => total,
+        None => {
+            eprintln!("The sweep space is too large.");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let threads = if args.threads == 0 {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        args.threads
+    };
+    // The sweep never uses more threads than combinations
+    let threads = threads.clamp(1, total.max(1));
+
+    println!(
+        "Sweeping {} combinations on {} threads, sorted by {:?}.",
+        total, threads, args.sort_by
+    );
+
+    let results = sweep::run_sweep(&space, &job_configs, threads, args.sort_by);
+    sweep::print_table(&results, args.top);
+
+    ExitCode::SUCCESS
+}
+
+fn run_optimize(args: OptimizeArgs) -> ExitCode {
+    let job_configs = match parse_job_configs(args.job_list) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+    let (job_configs, _) = release_periodic(&job_configs, None);
+
+    let (quantum, allotment, boost) = match (
+        parse_range(&args.quantum_range),
+        parse_range(&args.allotment_range),
+        parse_range(&args.boost_range),
+    ) {
+        (Some(quantum), Some(allotment), Some(boost)) if quantum.0 > 0 && allotment.0 > 0 => {
+            (quantum, allotment, boost)
+        }
+        _ => {
+            eprintln!("Ranges must be in the form lo-hi, with positive quantums and allotments.");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.max_queues == 0 {
+        eprintln!("max_queues must be positive.");
+        return ExitCode::FAILURE;
+    }
+
+    if !(0.0..=1.0).contains(&args.weight) {
+        eprintln!("weight must be between 0 and 1.");
+        return ExitCode::FAILURE;
+    }
+
+    let bounds = Bounds::new(args.max_queues, quantum, allotment, boost);
+    let goal = Goal::new(args.target, args.weight, args.starvation_cap);
+    let search = Search::new(args.method, args.iterations, args.seed, args.temperature);
+    let optimizer = Optimizer::new(&job_configs, bounds, goal, args.io_bump, args.stay);
+
+    let result = optimizer.run(&search);
+    let metrics = result.metrics();
+
+    println!(
+        "Best configuration found with cost {:.2}{}.",
+        result.cost(),
+        if optimizer.goal().is_feasible(metrics) {
+            ""
+        } else {
+            ", violating the starvation cap"
+        }
+    );
+    println!("{:?}", result.scheduler_config());
+    println!("{:?}", result.queue_configs());
+    println!(
+        "Equivalent arguments: -q {} -a {} -b {}{}{}",
+        result
+            .queue_configs()
+            .iter()
+            .map(|q| q.quantum().to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+        result
+            .queue_configs()
+            .iter()
+            .map(|q| q.allotment().to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+        result.scheduler_config().priority_boost_interval(),
+        if args.io_bump { " -i" } else { "" },
+        if args.stay { " -s" } else { "" }
+    );
+    println!(
+        "Average turnaround time: {}. P99 turnaround time: {}. Average response time: {}. Max wait time: {}.",
+        metrics.average_turnaround_time(),
+        metrics.p99_turnaround_time(),
+        metrics.average_response_time(),
+        metrics.max_wait_time()
+    );
+
+    ExitCode::SUCCESS
+}
 
+fn run_compare(args: CompareArgs) -> ExitCode {
+    let job_configs = match parse_job_configs(args.job_list) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+    let (job_configs, _) = release_periodic(&job_configs, args.horizon);
+
+    let scheduler_config = match parse_scheduler_config(args.boost, args.io_bump, args.stay) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+
+    let queue_configs = match parse_queue_configs(args.quantum_list, args.allotment_list) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+
+    let base = NamedConfig::new("base", scheduler_config, queue_configs);
+
+    let mut specs = args.configs;
+    if let Some(path) = args.config_file {
+        match std::fs::read_to_string(&path) {
+            Ok(content) => specs.extend(
+                content
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| line.to_string()),
+            ),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if specs.is_empty() {
+        eprintln!("At least one config is required.");
+        return ExitCode::FAILURE;
+    }
+
+    let mut configs = Vec::new();
+    for spec in specs.iter() {
+        match compare::parse_config(spec, &base) {
+            Some(config) => configs.push(config),
+            None => return ExitCode::FAILURE,
+        }
+    }
+
+    let baseline = match args.baseline {
+        Some(name) => match configs.iter().position(|c| c.name() == name) {
+            Some(index) => index,
+            None => {
+                eprintln!("Baseline {} is not one of the compared configs.", name);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => 0,
+    };
+
+    let outcomes = compare::run_all(&configs, &job_configs);
+    compare::print_report(&outcomes, baseline);
+    if args.gantt {
+        compare::print_timelines(&outcomes, args.gantt_width);
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn run_whatif(args: WhatifArgs) -> ExitCode {
+    let mut changes = Vec::new();
+    for item in args.changes.iter() {
+        match whatif::parse_change(item) {
+            Some(change) => changes.push(change),
+            None => return ExitCode::FAILURE,
+        }
+    }
+
+    let scheduler = match (args.resume, args.job_list) {
+        (Some(path), _) => match snapshot::load(&path) {
+            Some(scheduler) => scheduler,
+            None => return ExitCode::FAILURE,
+        },
+        (None, Some(job_list)) => {
+            let job_configs = match parse_job_configs(job_list) {
+                Some(config) => config,
+                None => return ExitCode::FAILURE,
+            };
+            let (job_configs, _) = release_periodic(&job_configs, args.horizon);
+
+            let scheduler_config = match parse_scheduler_config(args.boost, args.io_bump, args.stay)
+            {
+                Some(config) => config,
+                None => return ExitCode::FAILURE,
+            };
+
+            let queue_configs = match parse_queue_configs(args.quantum_list, args.allotment_list) {
+                Some(config) => config,
+                None => return ExitCode::FAILURE,
+            };
+
+            let mut scheduler = Scheduler::new(scheduler_config, queue_configs);
+            scheduler.set_trace(false);
+            scheduler.add_jobs(job_configs);
+            scheduler
+        }
+        (None, None) => return ExitCode::FAILURE,
+    };
+
+    match whatif::run(scheduler, args.at, &changes) {
+        Some((original, changed)) => {
+            whatif::print_report(original, changed, &args.changes);
+            ExitCode::SUCCESS
+        }
+        None => ExitCode::FAILURE,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn run_supervise(args: SuperviseArgs) -> ExitCode {
+    let scheduler_config = match parse_scheduler_config(args.boost, args.io_bump, args.stay) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+
+    let queue_configs = match parse_queue_configs(args.quantum_list, args.allotment_list) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+
+    if args.poll == 0 {
+        eprintln!("poll must be positive.");
+        return ExitCode::FAILURE;
+    }
+
+    let mut supervisor = Supervisor::new(
+        scheduler_config,
+        queue_configs,
+        Duration::from_millis(args.poll),
+    );
+    supervisor.set_trace(!args.quiet);
+
+    for command in args.commands.iter() {
+        if let Err(e) = supervisor.spawn(command) {
+            eprintln!("Failed to start {}: {}", command, e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    supervisor.run();
+    supervisor.print_report();
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run_supervise(_args: SuperviseArgs) -> ExitCode {
+    eprintln!("supervise needs Linux, for /proc and job control signals.");
+    ExitCode::FAILURE
+}
+
+#[cfg(target_os = "linux")]
+fn run_spool(args: SpoolArgs) -> ExitCode {
+    let socket = args.socket.unwrap_or_else(spool::default_socket);
+    let line = match args.action {
+        SpoolAction::Daemon(daemon_args) => return run_spool_daemon(daemon_args, &socket),
+        SpoolAction::Submit { command } => {
+            format!("submit {}", spool::escape(&spool::shell_join(&command)))
+        }
+        SpoolAction::List => "list".to_string(),
+        SpoolAction::Kill { id } => format!("kill {}", id),
+        SpoolAction::Renice { id, level } => format!("renice {} {}", id, level),
+        SpoolAction::Stats => "stats".to_string(),
+        SpoolAction::Shutdown => "shutdown".to_string(),
+    };
+
+    match spool::request(&socket, &line) {
+        Ok(response) if response.starts_with("error: ") => {
+            eprint!("{}", response);
+            ExitCode::FAILURE
+        }
+        Ok(response) => {
+            print!("{}", response);
             ExitCode::SUCCESS
         }
         Err(e) => {
-            eprintln!("{}", e);
+            eprintln!("Failed to reach the daemon at {}: {}", socket.display(), e);
             ExitCode::FAILURE
         }
     }
 }
 
+#[cfg(target_os = "linux")]
+fn run_spool_daemon(args: DaemonArgs, socket: &std::path::Path) -> ExitCode {
+    let scheduler_config = match parse_scheduler_config(args.boost, args.io_bump, args.stay) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+
+    let queue_configs = match parse_queue_configs(args.quantum_list, args.allotment_list) {
+        Some(config) => config,
+        None => return ExitCode::FAILURE,
+    };
+
+    if args.slots == 0 || args.poll == 0 {
+        eprintln!("slots and poll must be positive.");
+        return ExitCode::FAILURE;
+    }
+
+    let state = args.state.unwrap_or_else(spool::default_state);
+    let mut spooler = Spooler::new(scheduler_config, queue_configs, args.slots, state.clone());
+    spooler.set_trace(args.verbose);
+
+    match spooler.restore() {
+        Ok(0) => {}
+        Ok(restarted) => println!(
+            "Restarted {} unfinished jobs from {}.",
+            restarted,
+            state.display()
+        ),
+        Err(e) => {
+            eprintln!("Failed to restore {}: {}", state.display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    println!("Listening on {}.", socket.display());
+    if let Err(e) = spooler.serve(socket, Duration::from_millis(args.poll)) {
+        eprintln!("Failed to serve on {}: {}", socket.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run_spool(_args: SpoolArgs) -> ExitCode {
+    eprintln!("spool needs Linux, for /proc and job control signals.");
+    ExitCode::FAILURE
+}
+
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    let (lo, hi) = range.split_once('-')?;
+    let lo = lo.parse::<u32>().ok()?;
+    let hi = hi.parse::<u32>().ok()?;
+    if lo > hi {
+        return None;
+    }
+
+    Some((lo, hi))
+}
+
+fn parse_dispatch_table(table: &str, update_interval: u32) -> Option<DispatchTable> {
+    if update_interval == 0 {
+        eprintln!("ts_update must be positive.");
+        return None;
+    }
+
+    let mut table = if table == "default" {
+        DispatchTable::solaris_default()
+    } else {
+        let content = match std::fs::read_to_string(table) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", table, e);
+                return None;
+            }
+        };
+
+        match DispatchTable::parse(&content) {
+            Some(table) => table,
+            None => {
+                eprintln!("Invalid dispatch table in {}.", table);
+                return None;
+            }
+        }
+    };
+
+    table.set_update_interval(update_interval);
+    Some(table)
+}
+
 fn parse_scheduler_config(
     priority_boost_interval: u32,
     io_bump: bool,
@@ -144,44 +1129,258 @@ fn parse_queue_configs(quantums: String, allotments: String) -> Option<Vec<Queue
     Some(queue_config)
 }
 
-fn parse_job_configs(jobs: String) -> Option<Vec<JobConfig>> {
-    let mut all_job_valid = true;
-
-    let job_list = jobs
-        .split(':')
-        .map(|s| {
-            s.split(',')
-                .map(|x| match x.parse::<u32>() {
-                    Ok(x) => x,
-                    Err(_) => {
-                        all_job_valid = false;
-                        0
-                    }
+fn parse_aging(thresholds: &str, targets: Option<&str>, queue_configs: &mut [QueueConfig]) -> bool {
+    let threshold_list = match thresholds
+        .split(',')
+        .map(|x| x.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()
+    {
+        Some(list) => list,
+        None => {
+            eprintln!("Invalid value found in aging.");
+            return false;
+        }
+    };
+
+    if threshold_list.len() != queue_configs.len() {
+        eprintln!("aging must have one threshold per queue level");
+        return false;
+    }
+
+    let target_list = match targets {
+        Some(targets) => {
+            let list = targets
+                .split(',')
+                .map(|x| match x {
+                    "-" => Some(None),
+                    _ => x.parse::<usize>().ok().map(Some),
                 })
-                .collect::<Vec<u32>>()
-        })
-        .collect::<Vec<Vec<u32>>>();
+                .collect::<Option<Vec<Option<usize>>>>();
 
-    if !all_job_valid {
-        eprintln!("Invalid value found in job_list.");
-        return None;
+            match list {
+                Some(list) if list.len() == queue_configs.len() => list,
+                _ => {
+                    eprintln!("aging_target must have one target or '-' per queue level");
+                    return false;
+                }
+            }
+        }
+        None => vec![None; queue_configs.len()],
+    };
+
+    for (level, config) in queue_configs.iter_mut().enumerate() {
+        if let Some(target) = target_list[level] {
+            if target >= level {
+                eprintln!(
+                    "The aging target of level {} must be a higher level.",
+                    level
+                );
+                return false;
+            }
+        }
+
+        config.set_aging(threshold_list[level], target_list[level]);
     }
 
-    for job in job_list.iter() {
-        if job.len() != 4 {
-            all_job_valid = false;
-            break;
+    true
+}
+
+fn parse_disciplines(disciplines: &str, queue_configs: &mut [QueueConfig]) -> bool {
+    let discipline_list = match disciplines
+        .split(',')
+        .map(|x| Discipline::from_str(x, true).ok())
+        .collect::<Option<Vec<Discipline>>>()
+    {
+        Some(list) => list,
+        None => {
+            eprintln!("discipline must be one of rr, fcfs, srt or lottery per queue level");
+            return false;
         }
+    };
+
+    if discipline_list.len() != queue_configs.len() {
+        eprintln!("discipline must have one value per queue level");
+        return false;
     }
-    if !all_job_valid {
-        eprintln!("job_list must be in the form x1,y1,z1,u1:x2,y2,z2,u2:...");
+
+    for (config, discipline) in queue_configs.iter_mut().zip(discipline_list) {
+        config.set_discipline(discipline);
+    }
+
+    true
+}
+
+fn parse_job_configs(jobs: String) -> Option<Vec<JobConfig>> {
+    let mut job_configs = Vec::new();
+
+    for job in jobs.split(':') {
+        let fields = job.split(',').collect::<Vec<&str>>();
+        if fields.len() < 4 {
+            eprintln!("job_list must be in the form x1,y1,z1,u1:x2,y2,z2,u2:...");
+            return None;
+        }
+
+        let values = match fields[..4]
+            .iter()
+            .map(|x| x.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()
+        {
+            Some(values) => values,
+            None => {
+                eprintln!("Invalid value found in job_list.");
+                return None;
+            }
+        };
+
+        let mut job_config = JobConfig::new(values[0], values[1], values[2], values[3]);
+        for attribute in fields[4..].iter() {
+            if !parse_job_attribute(attribute, &mut job_config) {
+                eprintln!("Invalid job attribute {} found in job_list.", attribute);
+                return None;
+            }
+        }
+
+        if job_config.class().is_real_time() != (job_config.rt_priority() > 0) {
+            eprintln!("Jobs of class fifo or rr, and only those, need an rtprio in job_list.");
+            return None;
+        }
+
+        job_configs.push(job_config);
+    }
+
+    for (pid, job_config) in job_configs.iter().enumerate() {
+        match job_config.transfer_to() {
+            Some(target) if target as usize >= job_configs.len() || target as usize == pid => {
+                eprintln!(
+                    "Job {} transfers its tickets to an invalid job {}.",
+                    pid, target
+                );
+                return None;
+            }
+            _ => {}
+        }
+    }
+
+    // Releasing periodic jobs renumbers the pids, so a transfer target would be lost
+    if job_configs.iter().any(|j| j.transfer_to().is_some())
+        && job_configs.iter().any(|j| j.period() > 0)
+    {
+        eprintln!("Jobs that transfer tickets cannot be combined with periodic jobs.");
         return None;
     }
 
-    let job_configs = job_list
-        .iter()
-        .map(|job| JobConfig::new(job[0], job[1], job[2], job[3]))
-        .collect::<Vec<JobConfig>>();
+    if job_configs.iter().any(|j| !j.dependencies().is_empty()) {
+        if job_configs.iter().any(|j| j.period() > 0) {
+            eprintln!("Jobs with dependencies cannot be combined with periodic jobs.");
+            return None;
+        }
+        if dag::topological_order(&job_configs).is_none() {
+            eprintln!(
+                "Job dependencies must refer to other jobs in job_list and not form a cycle."
+            );
+            return None;
+        }
+    }
 
     Some(job_configs)
 }
+
+fn parse_events(events: String) -> Option<Vec<Event>> {
+    events.split(':').map(Event::parse).collect()
+}
+
+// Optional per-job attributes follow the four numbers of a job, in the form key=value
+fn parse_job_attribute(attribute: &str, job_config: &mut JobConfig) -> bool {
+    match attribute.split_once('=') {
+        Some(("nice", value)) => match value.parse::<i32>() {
+            Ok(nice) if (-20..=19).contains(&nice) => job_config.set_nice(nice),
+            _ => return false,
+        },
+        Some(("tickets", value)) => match value.parse::<u32>() {
+            Ok(tickets) if tickets > 0 => job_config.set_tickets(tickets),
+            _ => return false,
+        },
+        Some(("currency", value)) if !value.is_empty() => {
+            job_config.set_currency(Some(value.to_string()))
+        }
+        Some(("transfer", value)) => match value.parse::<u32>() {
+            Ok(pid) => job_config.set_transfer_to(Some(pid)),
+            _ => return false,
+        },
+        Some(("class", value)) => match SchedClass::from_str(value, true) {
+            Ok(class) => job_config.set_class(class, job_config.rt_priority()),
+            _ => return false,
+        },
+        Some(("period", value)) => match value.parse::<u32>() {
+            Ok(period) if period > 0 => job_config.set_period(period),
+            _ => return false,
+        },
+        Some(("deadline", value)) => match value.parse::<u32>() {
+            Ok(deadline) if deadline > 0 => job_config.set_deadline(Some(deadline)),
+            _ => return false,
+        },
+        Some(("after", value)) => match value
+            .split('+')
+            .map(|d| d.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()
+        {
+            Some(dependencies) => job_config.set_dependencies(dependencies),
+            None => return false,
+        },
+        Some(("spawn", value)) => match Spawn::parse(value) {
+            Some(spawn) if spawn.at() <= job_config.workload() => job_config.add_spawn(spawn),
+            _ => return false,
+        },
+        Some(("rtprio", value)) => match value.parse::<u32>() {
+            Ok(priority) if (1..=99).contains(&priority) => {
+                job_config.set_class(job_config.class(), priority)
+            }
+            _ => return false,
+        },
+        _ => return false,
+    }
+
+    true
+}
+
+// Replace periodic jobs by their instances, released until the horizon
+fn release_periodic(jobs: &[JobConfig], horizon: Option<u32>) -> (Vec<JobConfig>, Vec<usize>) {
+    let horizon = horizon.unwrap_or_else(|| periodic::hyperperiod(jobs));
+    periodic::release(jobs, horizon)
+}
+
+// Parse the currencies in the form NAME=TICKETS,... and check every job uses a known one
+fn parse_currencies(currencies: Option<&str>, jobs: &[JobConfig]) -> Option<Vec<(String, u32)>> {
+    let mut parsed = Vec::new();
+
+    for currency in currencies
+        .unwrap_or("")
+        .split(',')
+        .filter(|c| !c.is_empty())
+    {
+        match currency.split_once('=') {
+            Some((name, tickets)) if !name.is_empty() => match tickets.parse::<u32>() {
+                Ok(tickets) if tickets > 0 => parsed.push((name.to_string(), tickets)),
+                _ => {
+                    eprintln!("Invalid tickets of currency {}.", name);
+                    return None;
+                }
+            },
+            _ => {
+                eprintln!("currencies must be in the form NAME=TICKETS,...");
+                return None;
+            }
+        }
+    }
+
+    for job in jobs.iter() {
+        if let Some(name) = job.currency() {
+            if !parsed.iter().any(|(n, _)| n == name) {
+                eprintln!("Unknown currency {} found in job_list.", name);
+                return None;
+            }
+        }
+    }
+
+    Some(parsed)
+}
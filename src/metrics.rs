@@ -0,0 +1,89 @@
+// metrics.rs
+// Summary metrics collected from a finished simulation.
+// Author: Hank Bao
+
+use crate::scheduler::Scheduler;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Metrics {
+    idle_time: u32,
+    average_turnaround_time: u32,
+    average_response_time: u32,
+    makespan: u32,
+    p99_turnaround_time: u32,
+    max_wait_time: u32,
+    deadline_misses: u32,
+    max_lateness: Option<i64>,
+}
+
+impl Metrics {
+    pub fn idle_time(&self) -> u32 {
+        self.idle_time
+    }
+
+    pub fn average_turnaround_time(&self) -> u32 {
+        self.average_turnaround_time
+    }
+
+    pub fn average_response_time(&self) -> u32 {
+        self.average_response_time
+    }
+
+    pub fn makespan(&self) -> u32 {
+        self.makespan
+    }
+
+    pub fn p99_turnaround_time(&self) -> u32 {
+        self.p99_turnaround_time
+    }
+
+    // The longest time any job sat schedulable without running, a measure of starvation
+    pub fn max_wait_time(&self) -> u32 {
+        self.max_wait_time
+    }
+
+    // Jobs that finished after their deadline
+    pub fn deadline_misses(&self) -> u32 {
+        self.deadline_misses
+    }
+
+    // The latest finish relative to the deadline, None if no job has a deadline
+    pub fn max_lateness(&self) -> Option<i64> {
+        self.max_lateness
+    }
+}
+
+impl From<&Scheduler> for Metrics {
+    fn from(scheduler: &Scheduler) -> Self {
+        let finished = scheduler.finished_processes();
+        let mut turnarounds = finished
+            .iter()
+            .map(|p| p.turnaround_time())
+            .collect::<Vec<u32>>();
+        turnarounds.sort_unstable();
+
+        Metrics {
+            idle_time: scheduler.total_idle_time(),
+            average_turnaround_time: scheduler.average_turnaround_time(),
+            average_response_time: scheduler.average_response_time(),
+            makespan: scheduler.current_time(),
+            p99_turnaround_time: percentile(&turnarounds, 99),
+            max_wait_time: finished.iter().map(|p| p.longest_wait()).max().unwrap_or(0),
+            deadline_misses: finished
+                .iter()
+                .filter(|p| p.lateness().is_some_and(|l| l > 0))
+                .count() as u32,
+            max_lateness: finished.iter().filter_map(|p| p.lateness()).max(),
+        }
+    }
+}
+
+// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u32], p: usize) -> u32 {
+    if sorted.is_empty() {
+        return 0;
+    }
+
+    let rank = (sorted.len() * p).div_ceil(100);
+    sorted[rank.max(1) - 1]
+}
@@ -0,0 +1,298 @@
+// optimize.rs
+// Local search for a configuration that minimizes a target metric on a workload.
+// Author: Hank Bao
+
+use crate::config::{JobConfig, QueueConfig, SchedulerConfig};
+use crate::metrics::Metrics;
+use crate::rng::Rng;
+use crate::sweep;
+
+// Cost added per tick a job waited beyond the starvation cap
+const STARVATION_PENALTY: f64 = 100.0;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum Method {
+    HillClimb,
+    Anneal,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum Target {
+    MeanResponse,
+    P99Turnaround,
+    Weighted,
+}
+
+pub struct Goal {
+    target: Target,
+    // Weight of the mean response time in the weighted mix, the rest goes to p99 turnaround
+    weight: f64,
+    starvation_cap: Option<u32>,
+}
+
+impl Goal {
+    pub fn new(target: Target, weight: f64, starvation_cap: Option<u32>) -> Goal {
+        Goal {
+            target,
+            weight,
+            starvation_cap,
+        }
+    }
+
+    fn score(&self, metrics: &Metrics) -> f64 {
+        let response = metrics.average_response_time() as f64;
+        let p99 = metrics.p99_turnaround_time() as f64;
+        match self.target {
+            Target::MeanResponse => response,
+            Target::P99Turnaround => p99,
+            Target::Weighted => self.weight * response + (1.0 - self.weight) * p99,
+        }
+    }
+
+    fn excess_wait(&self, metrics: &Metrics) -> u32 {
+        match self.starvation_cap {
+            Some(cap) => metrics.max_wait_time().saturating_sub(cap),
+            None => 0,
+        }
+    }
+
+    fn cost(&self, metrics: &Metrics) -> f64 {
+        // The starvation cap is a soft constraint so the search can walk out of infeasible regions
+        self.score(metrics) + STARVATION_PENALTY * self.excess_wait(metrics) as f64
+    }
+
+    pub fn is_feasible(&self, metrics: &Metrics) -> bool {
+        self.excess_wait(metrics) == 0
+    }
+}
+
+pub struct Bounds {
+    max_queues: usize,
+    quantum: (u32, u32),
+    allotment: (u32, u32),
+    boost: (u32, u32),
+}
+
+impl Bounds {
+    pub fn new(
+        max_queues: usize,
+        quantum: (u32, u32),
+        allotment: (u32, u32),
+        boost: (u32, u32),
+    ) -> Bounds {
+        assert!(max_queues > 0);
+        assert!(0 < quantum.0 && quantum.0 <= quantum.1);
+        assert!(0 < allotment.0 && allotment.0 <= allotment.1);
+        assert!(boost.0 <= boost.1);
+        Bounds {
+            max_queues,
+            quantum,
+            allotment,
+            boost,
+        }
+    }
+}
+
+pub struct Search {
+    method: Method,
+    iterations: u32,
+    seed: u64,
+    temperature: f64,
+}
+
+impl Search {
+    pub fn new(method: Method, iterations: u32, seed: u64, temperature: f64) -> Search {
+        Search {
+            method,
+            iterations,
+            seed,
+            temperature,
+        }
+    }
+}
+
+// A point in the search space: (quantum, allotment) per level, and the boost interval
+#[derive(Clone, Debug)]
+struct Candidate {
+    levels: Vec<(u32, u32)>,
+    boost: u32,
+}
+
+pub struct OptimizeResult {
+    scheduler_config: SchedulerConfig,
+    queue_configs: Vec<QueueConfig>,
+    metrics: Metrics,
+    cost: f64,
+}
+
+impl OptimizeResult {
+    pub fn scheduler_config(&self) -> SchedulerConfig {
+        self.scheduler_config
+    }
+
+    pub fn queue_configs(&self) -> &[QueueConfig] {
+        &self.queue_configs
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+}
+
+pub struct Optimizer<'a> {
+    jobs: &'a [JobConfig],
+    bounds: Bounds,
+    goal: Goal,
+    io_bump: bool,
+    io_stay: bool,
+}
+
+impl<'a> Optimizer<'a> {
+    pub fn new(
+        jobs: &'a [JobConfig],
+        bounds: Bounds,
+        goal: Goal,
+        io_bump: bool,
+        io_stay: bool,
+    ) -> Optimizer<'a> {
+        Optimizer {
+            jobs,
+            bounds,
+            goal,
+            io_bump,
+            io_stay,
+        }
+    }
+
+    pub fn goal(&self) -> &Goal {
+        &self.goal
+    }
+
+    // Search for the configuration with the lowest cost, printing a convergence log
+    pub fn run(&self, search: &Search) -> OptimizeResult {
+        let mut rng = Rng::new(search.seed);
+
+        let mut current = self.initial_candidate();
+        let (mut current_cost, mut current_metrics) = self.evaluate(&current);
+        let mut best = current.clone();
+        let mut best_cost = current_cost;
+        let mut best_metrics = current_metrics;
+
+        println!("[0] cost {:.2} {:?}", best_cost, best);
+
+        for i in 1..=search.iterations {
+            let next = self.neighbor(&current, &mut rng);
+            let (next_cost, next_metrics) = self.evaluate(&next);
+
+            let accept = match search.method {
+                Method::HillClimb => next_cost <= current_cost,
+                Method::Anneal => {
+                    // Geometric cooling from the initial temperature down to a thousandth of it
+                    let progress = i as f64 / search.iterations as f64;
+                    let t = search.temperature * 0.001f64.powf(progress);
+                    next_cost <= current_cost
+                        || rng.next_f64() < ((current_cost - next_cost) / t).exp()
+                }
+            };
+
+            if accept {
+                current = next;
+                current_cost = next_cost;
+                current_metrics = next_metrics;
+
+                if current_cost < best_cost {
+                    best = current.clone();
+                    best_cost = current_cost;
+                    best_metrics = current_metrics;
+                    println!("[{}] cost {:.2} {:?}", i, best_cost, best);
+                }
+            }
+        }
+
+        let (scheduler_config, queue_configs) = self.configs(&best);
+        OptimizeResult {
+            scheduler_config,
+            queue_configs,
+            metrics: best_metrics,
+            cost: best_cost,
+        }
+    }
+
+    // Start from a conventional shape: up to three levels with doubling quantums
+    fn initial_candidate(&self) -> Candidate {
+        let (q_lo, q_hi) = self.bounds.quantum;
+        let (a_lo, a_hi) = self.bounds.allotment;
+        let levels = (0..self.bounds.max_queues.min(3))
+            .map(|l| {
+                let quantum = (q_lo.saturating_mul(1 << l)).clamp(q_lo, q_hi);
+                let allotment = quantum.saturating_mul(2).clamp(a_lo, a_hi);
+                (quantum, allotment)
+            })
+            .collect();
+
+        Candidate {
+            levels,
+            boost: self.bounds.boost.0,
+        }
+    }
+
+    fn neighbor(&self, candidate: &Candidate, rng: &mut Rng) -> Candidate {
+        let mut next = candidate.clone();
+        let level = rng.below(next.levels.len() as u32) as usize;
+
+        match rng.below(5) {
+            0 => {
+                let (lo, hi) = self.bounds.quantum;
+                next.levels[level].0 = tweak(next.levels[level].0, lo, hi, rng);
+            }
+            1 => {
+                let (lo, hi) = self.bounds.allotment;
+                next.levels[level].1 = tweak(next.levels[level].1, lo, hi, rng);
+            }
+            2 if next.levels.len() < self.bounds.max_queues => {
+                let last = *next.levels.last().unwrap();
+                next.levels.push(last);
+            }
+            3 if next.levels.len() > 1 => {
+                next.levels.remove(level);
+            }
+            _ => {
+                let (lo, hi) = self.bounds.boost;
+                next.boost = tweak(next.boost, lo, hi, rng);
+            }
+        }
+
+        next
+    }
+
+    fn configs(&self, candidate: &Candidate) -> (SchedulerConfig, Vec<QueueConfig>) {
+        let scheduler_config = SchedulerConfig::new(candidate.boost, self.io_bump, self.io_stay);
+        let queue_configs = candidate
+            .levels
+            .iter()
+            .map(|&(quantum, allotment)| QueueConfig::new(quantum, allotment, true))
+            .collect();
+
+        (scheduler_config, queue_configs)
+    }
+
+    fn evaluate(&self, candidate: &Candidate) -> (f64, Metrics) {
+        let (scheduler_config, queue_configs) = self.configs(candidate);
+        let metrics = sweep::simulate(scheduler_config, queue_configs, self.jobs);
+        (self.goal.cost(&metrics), metrics)
+    }
+}
+
+// Move a value up or down by a random step of up to a quarter of its range
+fn tweak(value: u32, lo: u32, hi: u32, rng: &mut Rng) -> u32 {
+    let step = rng.range(1, ((hi - lo) / 4).max(1));
+    if rng.below(2) == 0 {
+        value.saturating_sub(step).max(lo)
+    } else {
+        value.saturating_add(step).min(hi)
+    }
+}
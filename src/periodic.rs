@@ -0,0 +1,164 @@
+// periodic.rs
+// Periodic real-time tasks: release of their instances, deadline results and the classic
+// utilization-bound schedulability tests for EDF and Rate-Monotonic scheduling.
+// Author: Hank Bao
+
+use crate::config::JobConfig;
+use crate::process::Process;
+
+// Longest horizon used when the hyperperiod of the tasks is larger, or overflows
+pub const MAX_HORIZON: u32 = 1_000_000;
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// The least common multiple of the periods plus the latest first release, capped at MAX_HORIZON
+pub fn hyperperiod(jobs: &[JobConfig]) -> u32 {
+    let mut lcm = 1u64;
+    for job in jobs.iter().filter(|j| j.period() > 0) {
+        lcm = lcm / gcd(lcm, job.period() as u64) * job.period() as u64;
+        if lcm > MAX_HORIZON as u64 {
+            return MAX_HORIZON;
+        }
+    }
+
+    let phase = jobs
+        .iter()
+        .filter(|j| j.period() > 0)
+        .map(|j| j.arrival_time() as u64)
+        .max()
+        .unwrap_or(0);
+    (lcm + phase).min(MAX_HORIZON as u64) as u32
+}
+
+// Replace every periodic job by its instances released before the horizon, and return
+// the index of the job each resulting job came from. Instances keep the period of their
+// task, for rate-monotonic priorities, and their deadline defaults to the period.
+pub fn release(jobs: &[JobConfig], horizon: u32) -> (Vec<JobConfig>, Vec<usize>) {
+    let mut released = Vec::new();
+    let mut tasks = Vec::new();
+
+    for (task, job) in jobs.iter().enumerate() {
+        if job.period() == 0 {
+            released.push(job.clone());
+            tasks.push(task);
+            continue;
+        }
+
+        let deadline = job.deadline().unwrap_or(job.period());
+        let mut release = job.arrival_time();
+        while release < horizon {
+            let mut instance = job.clone();
+            instance.set_arrival_time(release);
+            instance.set_deadline(Some(deadline));
+            released.push(instance);
+            tasks.push(task);
+
+            release = match release.checked_add(job.period()) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+
+    (released, tasks)
+}
+
+// Deadline misses and lateness per task, then the utilization-bound analysis
+pub fn print_report(jobs: &[JobConfig], tasks: &[usize], finished: &[Process]) {
+    println!("Deadlines per task:");
+    for (task, job) in jobs.iter().enumerate() {
+        let lateness = finished
+            .iter()
+            // Spawned children come after the released jobs, and have no deadline
+            .filter(|p| tasks.get(p.pid() as usize) == Some(&task))
+            .filter_map(|p| p.lateness())
+            .collect::<Vec<i64>>();
+        if lateness.is_empty() {
+            continue;
+        }
+
+        let misses = lateness.iter().filter(|&&l| l > 0).count();
+        let kind = if job.period() > 0 {
+            format!("period {}", job.period())
+        } else {
+            "one-off".to_string()
+        };
+        println!(
+            "Task {} ({}, WCET {}): {} of {} instances missed, max lateness {}, average lateness {:.1}.",
+            task,
+            kind,
+            job.workload(),
+            misses,
+            lateness.len(),
+            lateness.iter().max().unwrap(),
+            lateness.iter().sum::<i64>() as f64 / lateness.len() as f64
+        );
+    }
+
+    let periodic = jobs.iter().filter(|j| j.period() > 0).collect::<Vec<_>>();
+    if periodic.is_empty() {
+        return;
+    }
+
+    // U = sum of C/T, and the density sum of C/min(D, T) for constrained deadlines
+    let n = periodic.len() as f64;
+    let utilization = periodic
+        .iter()
+        .map(|j| j.workload() as f64 / j.period() as f64)
+        .sum::<f64>();
+    let density = periodic
+        .iter()
+        .map(|j| {
+            let window = j.deadline().unwrap_or(j.period()).min(j.period());
+            j.workload() as f64 / window.max(1) as f64
+        })
+        .sum::<f64>();
+    let implicit = periodic
+        .iter()
+        .all(|j| j.deadline().is_none_or(|d| d >= j.period()));
+    // Liu and Layland: n(2^(1/n) - 1)
+    let rms_bound = n * (2f64.powf(1.0 / n) - 1.0);
+
+    println!("Utilization analysis, ignoring I/O:");
+    println!(
+        "Utilization {:.3} of {} periodic tasks.",
+        utilization,
+        periodic.len()
+    );
+
+    let edf = if implicit {
+        if utilization <= 1.0 {
+            "schedulable, U <= 1".to_string()
+        } else {
+            "not schedulable, U > 1".to_string()
+        }
+    } else if density <= 1.0 {
+        format!("schedulable, density {:.3} <= 1", density)
+    } else if utilization > 1.0 {
+        "not schedulable, U > 1".to_string()
+    } else {
+        format!("inconclusive, density {:.3} > 1", density)
+    };
+    println!("EDF: {}.", edf);
+
+    let rms = if !implicit {
+        "the Liu and Layland bound needs deadlines equal to periods".to_string()
+    } else if utilization <= rms_bound {
+        format!("schedulable, U <= bound {:.3}", rms_bound)
+    } else if utilization <= 1.0 {
+        format!(
+            "inconclusive, U > bound {:.3}, needs response time analysis",
+            rms_bound
+        )
+    } else {
+        "not schedulable, U > 1".to_string()
+    };
+    println!("RMS: {}.", rms);
+    println!("MLFQ: no guarantee, priorities follow CPU usage rather than deadlines.");
+}
@@ -0,0 +1,125 @@
+// policy.rs
+// Scheduling policies that replace the MLFQ rules while reusing the queues, traces and metrics.
+// Author: Hank Bao
+
+pub mod bsd;
+pub mod cfs;
+pub mod deadline;
+pub mod lottery;
+pub mod o1;
+pub mod ule;
+
+use crate::config::{JobConfig, QueueConfig};
+use crate::process::Process;
+use crate::queue::Queue;
+
+// The built-in policies, created with their default settings
+#[derive(Copy, Clone, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum PolicyKind {
+    Bsd,
+    Ule,
+    O1,
+    Cfs,
+    Eevdf,
+    Lottery,
+    Stride,
+    Edf,
+    Rms,
+}
+
+impl PolicyKind {
+    pub fn create(&self, options: &PolicyOptions) -> Box<dyn Policy> {
+        match self {
+            PolicyKind::Bsd => Box::new(bsd::Bsd::default()),
+            PolicyKind::Ule => Box::new(ule::Ule::with_threshold(options.interact_threshold)),
+            PolicyKind::O1 => Box::new(o1::O1::new()),
+            PolicyKind::Cfs => Box::new(cfs::Cfs::default()),
+            PolicyKind::Eevdf => Box::new(cfs::Cfs::eevdf()),
+            PolicyKind::Lottery => {
+                Box::new(lottery::Lottery::new(options.seed, 10, &options.currencies))
+            }
+            PolicyKind::Stride => Box::new(lottery::Lottery::stride(10, &options.currencies)),
+            PolicyKind::Edf => Box::new(deadline::Deadline::edf()),
+            PolicyKind::Rms => Box::new(deadline::Deadline::rate_monotonic()),
+        }
+    }
+}
+
+// Settings of the built-in policies that can be changed from the command line
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PolicyOptions {
+    interact_threshold: u32,
+    seed: u64,
+    currencies: Vec<(String, u32)>,
+}
+
+impl PolicyOptions {
+    pub fn set_interact_threshold(&mut self, threshold: u32) {
+        self.interact_threshold = threshold;
+    }
+
+    // Seed of the lottery draws
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    // Currencies of the proportional-share policies, as (name, base tickets funding it)
+    pub fn set_currencies(&mut self, currencies: Vec<(String, u32)>) {
+        self.currencies = currencies;
+    }
+}
+
+impl Default for PolicyOptions {
+    fn default() -> Self {
+        PolicyOptions {
+            interact_threshold: ule::Ule::DEFAULT_THRESHOLD,
+            seed: 0,
+            currencies: Vec::new(),
+        }
+    }
+}
+
+// A policy decides where each process lives in the queues and which one runs next.
+// The scheduler owns the processes and calls back into the policy at every decision point.
+pub trait Policy: Send {
+    fn name(&self) -> &'static str;
+
+    // The queues the policy maps its priorities onto, highest priority first
+    fn queue_configs(&self) -> Vec<QueueConfig>;
+
+    // The queue a new job enters
+    fn admit(&mut self, process: &Process, job: &JobConfig, now: u32) -> usize;
+
+    // Periodic work before every decision, e.g. decaying priorities.
+    // Returns the processes to move as (pid, target queue).
+    fn update(&mut self, _queues: &[Queue], _now: u32) -> Vec<(u32, usize)> {
+        Vec::new()
+    }
+
+    // Pick the process to run as (queue, pid). By default the first schedulable
+    // process of the highest priority queue, i.e. round-robin within a level.
+    fn pick(&mut self, queues: &[Queue], now: u32) -> Option<(usize, u32)> {
+        queues.iter().enumerate().find_map(|(i, q)| {
+            q.processes()
+                .find(|p| p.next_schedule_time() <= now)
+                .map(|p| (i, p.pid()))
+        })
+    }
+
+    // The time slice to give the process
+    fn quantum(&self, process: &Process, queue: usize) -> u32;
+
+    // Called after the process ran without finishing, returns the queue it goes to
+    fn ran(&mut self, process: &Process, queue: usize, run_time: u32, now: u32) -> usize;
+
+    // Called when a process finishes
+    fn finished(&mut self, _process: &Process, _now: u32) {}
+
+    // Print policy specific results after the run
+    fn report(&self) {}
+}
+
+// Queue configs for a policy that hands out its own time slices and never uses allotments
+pub fn unlimited_queue_configs(levels: usize, quantum: u32) -> Vec<QueueConfig> {
+    vec![QueueConfig::new(quantum, u32::MAX, false); levels]
+}
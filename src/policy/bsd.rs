@@ -0,0 +1,141 @@
+// bsd.rs
+// 4.4BSD decay-usage scheduler: priority follows recent CPU usage (p_estcpu) and nice,
+// and the usage decays once per second by a factor that depends on the load average.
+// Author: Hank Bao
+
+use std::collections::BTreeMap;
+
+use super::{unlimited_queue_configs, Policy};
+use crate::config::{JobConfig, QueueConfig};
+use crate::process::Process;
+use crate::queue::Queue;
+
+const PUSER: i32 = 50;
+const MAXPRI: i32 = 127;
+const NICE_WEIGHT: i32 = 2;
+// The best user priority, reached with nice -20 and no CPU usage
+const MINPRI: i32 = PUSER + NICE_WEIGHT * -20;
+// Priorities per run queue, 4.4BSD keeps 32 run queues of 4 priorities each
+const PPQ: i32 = 4;
+
+struct BsdState {
+    estcpu: f64,
+    nice: i32,
+    queue: usize,
+}
+
+impl BsdState {
+    fn priority(&self) -> i32 {
+        (PUSER + (self.estcpu / 4.0) as i32 + NICE_WEIGHT * self.nice).clamp(MINPRI, MAXPRI)
+    }
+
+    // User priorities from MINPRI to MAXPRI map onto queues 0.. in steps of PPQ
+    fn queue(&self) -> usize {
+        ((self.priority() - MINPRI) / PPQ) as usize
+    }
+}
+
+pub struct Bsd {
+    // Ticks per second, the interval of schedcpu
+    hz: u32,
+    // Round-robin time slice
+    rrticks: u32,
+    loadav: f64,
+    last_decay: u32,
+    states: BTreeMap<u32, BsdState>,
+}
+
+impl Bsd {
+    pub fn new(hz: u32, rrticks: u32) -> Bsd {
+        assert!(hz > 0 && rrticks > 0);
+        Bsd {
+            hz,
+            rrticks,
+            loadav: 0.0,
+            last_decay: 0,
+            states: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for Bsd {
+    // 100 ticks per second and a 100ms time slice, as in 4.4BSD
+    fn default() -> Self {
+        Bsd::new(100, 10)
+    }
+}
+
+impl Policy for Bsd {
+    fn name(&self) -> &'static str {
+        "4.4BSD"
+    }
+
+    fn queue_configs(&self) -> Vec<QueueConfig> {
+        let levels = ((MAXPRI - MINPRI) / PPQ + 1) as usize;
+        unlimited_queue_configs(levels, self.rrticks)
+    }
+
+    fn admit(&mut self, process: &Process, job: &JobConfig, _now: u32) -> usize {
+        let mut state = BsdState {
+            estcpu: 0.0,
+            nice: job.nice(),
+            queue: 0,
+        };
+        state.queue = state.queue();
+
+        let queue = state.queue;
+        self.states.insert(process.pid(), state);
+        queue
+    }
+
+    // schedcpu: once per second, update the load average and decay every estcpu
+    fn update(&mut self, queues: &[Queue], now: u32) -> Vec<(u32, usize)> {
+        let mut moves = Vec::new();
+
+        while now - self.last_decay >= self.hz {
+            self.last_decay += self.hz;
+
+            // One minute load average, sampled once per second
+            let runnable = queues
+                .iter()
+                .flat_map(|q| q.processes())
+                .filter(|p| p.next_schedule_time() <= now)
+                .count() as f64;
+            let exp = (-1.0f64 / 60.0).exp();
+            self.loadav = self.loadav * exp + runnable * (1.0 - exp);
+
+            let loadfac = 2.0 * self.loadav;
+            for (&pid, state) in self.states.iter_mut() {
+                state.estcpu =
+                    (loadfac / (loadfac + 1.0) * state.estcpu + state.nice as f64).max(0.0);
+
+                let queue = state.queue();
+                if queue != state.queue {
+                    state.queue = queue;
+                    moves.push((pid, queue));
+                }
+            }
+        }
+
+        moves
+    }
+
+    fn quantum(&self, _process: &Process, _queue: usize) -> u32 {
+        self.rrticks
+    }
+
+    // Every tick on the CPU adds to estcpu, then resetpriority picks the new queue
+    fn ran(&mut self, process: &Process, _queue: usize, run_time: u32, _now: u32) -> usize {
+        let state = self
+            .states
+            .get_mut(&process.pid())
+            .expect("process not admitted");
+        state.estcpu += run_time as f64;
+        state.queue = state.queue();
+        state.queue
+    }
+
+    fn finished(&mut self, process: &Process, _now: u32) {
+        self.states.remove(&process.pid());
+    }
+}
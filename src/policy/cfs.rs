@@ -0,0 +1,273 @@
+// cfs.rs
+// Linux CFS: every job accrues vruntime, its run time scaled by the weight of its nice value,
+// and the job with the lowest vruntime runs next. The EEVDF variant instead runs the eligible
+// job (non-negative lag) with the earliest virtual deadline, and keeps the lag across sleeps.
+// All jobs share a single queue.
+// Author: Hank Bao
+
+use std::collections::BTreeMap;
+
+use super::{unlimited_queue_configs, Policy};
+use crate::config::{JobConfig, QueueConfig};
+use crate::process::Process;
+use crate::queue::Queue;
+
+// sched_prio_to_weight, from nice -20 to 19. Nice 0 weighs NICE_0_LOAD.
+const PRIO_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+const NICE_0_LOAD: u64 = 1024;
+// Virtual times are kept in thousandths of a tick so low weights do not round to nothing
+const SCALE: u64 = 1000;
+
+struct Entity {
+    weight: u64,
+    vruntime: u64,
+    // EEVDF: vruntime at which the current request is due
+    deadline: u64,
+    // EEVDF: ticks of the current request already used
+    used: u32,
+    // Lag (avg_vruntime - vruntime) saved when the job went to sleep
+    lag: i64,
+    sleeping: bool,
+    // Not run yet, placed with START_DEBIT rather than as a sleeper
+    fresh: bool,
+    runtime: u32,
+}
+
+impl Entity {
+    // Convert ticks of wall time into virtual time for this job
+    fn delta(&self, ticks: u32) -> u64 {
+        ticks as u64 * SCALE * NICE_0_LOAD / self.weight
+    }
+}
+
+pub struct Cfs {
+    eevdf: bool,
+    // sched_latency: the period in which every runnable job should run once
+    latency: u32,
+    // sched_min_granularity, and the request size (base_slice) under EEVDF
+    min_granularity: u32,
+    entities: BTreeMap<u32, Entity>,
+    min_vruntime: u64,
+    avg_vruntime: u64,
+    // Slice computed for the job picked last
+    slice: u32,
+    finish_times: BTreeMap<u32, u32>,
+}
+
+impl Cfs {
+    pub fn new(eevdf: bool, latency: u32, min_granularity: u32) -> Cfs {
+        assert!(latency > 0 && min_granularity > 0);
+        Cfs {
+            eevdf,
+            latency,
+            min_granularity,
+            entities: BTreeMap::new(),
+            min_vruntime: 0,
+            avg_vruntime: 0,
+            slice: min_granularity,
+            finish_times: BTreeMap::new(),
+        }
+    }
+
+    // EEVDF with the default latency and slice
+    pub fn eevdf() -> Cfs {
+        Cfs::new(true, 24, 3)
+    }
+
+    // Weighted average vruntime of the given jobs, the zero-lag point of EEVDF
+    fn average(&self, pids: &[u32]) -> Option<u64> {
+        let (sum, weight) =
+            pids.iter()
+                .map(|pid| &self.entities[pid])
+                .fold((0u128, 0u128), |(sum, weight), e| {
+                    (
+                        sum + e.vruntime as u128 * e.weight as u128,
+                        weight + e.weight as u128,
+                    )
+                });
+        (weight > 0).then(|| (sum / weight) as u64)
+    }
+
+    // place_entity: set the vruntime of a job that starts or wakes up
+    fn place(&mut self, pid: u32, awake: &[u32]) {
+        let avg = self.average(awake).unwrap_or(self.min_vruntime);
+        let min_vruntime = self.min_vruntime;
+        let latency = self.latency;
+        let eevdf = self.eevdf;
+        let entity = self.entities.get_mut(&pid).expect("process not admitted");
+        let vslice = entity.delta(self.min_granularity);
+
+        if eevdf {
+            entity.vruntime = (avg as i64 - entity.lag).max(0) as u64;
+            entity.deadline = entity.vruntime + vslice;
+            entity.used = 0;
+        } else if entity.fresh {
+            // START_DEBIT: a new job starts one slice behind so it cannot hog the CPU
+            entity.vruntime = entity.vruntime.max(min_vruntime + vslice);
+        } else {
+            // Sleepers get credit of half a latency period, but never go backwards
+            let credit = latency as u64 * SCALE / 2;
+            entity.vruntime = entity.vruntime.max(min_vruntime.saturating_sub(credit));
+        }
+
+        entity.sleeping = false;
+        entity.fresh = false;
+    }
+}
+
+impl Default for Cfs {
+    // A 24 tick latency and a 3 tick granularity, the Linux defaults on 8 CPUs in milliseconds
+    fn default() -> Self {
+        Cfs::new(false, 24, 3)
+    }
+}
+
+impl Policy for Cfs {
+    fn name(&self) -> &'static str {
+        if self.eevdf {
+            "EEVDF"
+        } else {
+            "CFS"
+        }
+    }
+
+    fn queue_configs(&self) -> Vec<QueueConfig> {
+        unlimited_queue_configs(1, self.min_granularity)
+    }
+
+    fn admit(&mut self, process: &Process, job: &JobConfig, _now: u32) -> usize {
+        let entity = Entity {
+            weight: PRIO_TO_WEIGHT[(job.nice() + 20) as usize],
+            vruntime: 0,
+            deadline: 0,
+            used: 0,
+            lag: 0,
+            sleeping: true,
+            fresh: true,
+            runtime: 0,
+        };
+        self.entities.insert(process.pid(), entity);
+        0
+    }
+
+    fn pick(&mut self, queues: &[Queue], now: u32) -> Option<(usize, u32)> {
+        let runnable = queues
+            .iter()
+            .flat_map(|q| q.processes())
+            .filter(|p| p.next_schedule_time() <= now)
+            .map(|p| p.pid())
+            .collect::<Vec<u32>>();
+        if runnable.is_empty() {
+            return None;
+        }
+
+        // Place the jobs that arrived or woke up since the last pick
+        let mut awake = runnable
+            .iter()
+            .copied()
+            .filter(|pid| !self.entities[pid].sleeping)
+            .collect::<Vec<u32>>();
+        for &pid in runnable.iter() {
+            if self.entities[&pid].sleeping {
+                self.place(pid, &awake);
+                awake.push(pid);
+            }
+        }
+
+        let lowest = runnable
+            .iter()
+            .map(|pid| self.entities[pid].vruntime)
+            .min()
+            .unwrap_or(0);
+        self.min_vruntime = self.min_vruntime.max(lowest);
+        self.avg_vruntime = self.average(&runnable).unwrap_or(self.min_vruntime);
+
+        let pid = if self.eevdf {
+            // The job with the lowest vruntime is always eligible
+            let avg = self.avg_vruntime.max(lowest);
+            let pid = runnable
+                .iter()
+                .copied()
+                .filter(|pid| self.entities[pid].vruntime <= avg)
+                .min_by_key(|pid| self.entities[pid].deadline)?;
+            let entity = &self.entities[&pid];
+            self.slice = self.min_granularity - entity.used;
+            pid
+        } else {
+            let pid = runnable
+                .iter()
+                .copied()
+                .min_by_key(|pid| self.entities[pid].vruntime)?;
+
+            // sched_slice: a share of the latency period by weight, at least the granularity
+            let total = runnable
+                .iter()
+                .map(|pid| self.entities[pid].weight)
+                .sum::<u64>();
+            let share = self.latency as u64 * self.entities[&pid].weight / total;
+            self.slice = (share as u32).max(self.min_granularity);
+            pid
+        };
+
+        Some((0, pid))
+    }
+
+    fn quantum(&self, _process: &Process, _queue: usize) -> u32 {
+        self.slice
+    }
+
+    fn ran(&mut self, process: &Process, _queue: usize, run_time: u32, _now: u32) -> usize {
+        let min_granularity = self.min_granularity;
+        let avg = self.avg_vruntime;
+        let entity = self
+            .entities
+            .get_mut(&process.pid())
+            .expect("process not admitted");
+
+        entity.vruntime += entity.delta(run_time);
+        entity.runtime += run_time;
+
+        // EEVDF: a finished request gets a new deadline one virtual slice later
+        entity.used += run_time;
+        if entity.used >= min_granularity {
+            entity.used = 0;
+            entity.deadline = entity.vruntime + entity.delta(min_granularity);
+        }
+
+        if process.is_blocked() {
+            // Keep the lag, bounded by one slice either way, so sleeping neither earns nor loses much
+            let bound = entity.delta(min_granularity) as i64;
+            entity.lag = (avg as i64 - entity.vruntime as i64).clamp(-bound, bound);
+            entity.sleeping = true;
+        }
+
+        0
+    }
+
+    fn finished(&mut self, process: &Process, now: u32) {
+        // Charge the final run, which never reaches ran()
+        if let Some(entity) = self.entities.get_mut(&process.pid()) {
+            entity.vruntime += entity.delta(process.work_done() - entity.runtime);
+            entity.runtime = process.work_done();
+        }
+        self.finish_times.insert(process.pid(), now);
+    }
+
+    fn report(&self) {
+        println!("Virtual runtime at finish ({}):", self.name());
+        for (pid, entity) in self.entities.iter() {
+            println!(
+                "Process {} weight {}, ran {} ticks, vruntime {:.2}, finished at {}.",
+                pid,
+                entity.weight,
+                entity.runtime,
+                entity.vruntime as f64 / SCALE as f64,
+                self.finish_times.get(pid).copied().unwrap_or(0)
+            );
+        }
+    }
+}
@@ -0,0 +1,96 @@
+// deadline.rs
+// Real-time policies for periodic jobs: Earliest Deadline First, which runs the job with the
+// nearest absolute deadline, and Rate-Monotonic, a fixed priority where a shorter period wins.
+// Both preempt at every release. Jobs without a deadline or period run last.
+// Author: Hank Bao
+
+use std::collections::BTreeMap;
+
+use super::{unlimited_queue_configs, Policy};
+use crate::config::{JobConfig, QueueConfig};
+use crate::process::Process;
+use crate::queue::Queue;
+
+pub struct Deadline {
+    rate_monotonic: bool,
+    periods: BTreeMap<u32, u32>,
+    // Time until the next release, when the choice has to be made again
+    slice: u32,
+}
+
+impl Deadline {
+    pub fn edf() -> Deadline {
+        Deadline {
+            rate_monotonic: false,
+            periods: BTreeMap::new(),
+            slice: u32::MAX,
+        }
+    }
+
+    pub fn rate_monotonic() -> Deadline {
+        Deadline {
+            rate_monotonic: true,
+            ..Deadline::edf()
+        }
+    }
+
+    // Smaller runs first, ties go to the earlier deadline and then the lower pid
+    fn key(&self, process: &Process) -> (u32, u32, u32) {
+        let deadline = process.deadline().unwrap_or(u32::MAX);
+        let first = if self.rate_monotonic {
+            match self.periods[&process.pid()] {
+                0 => u32::MAX,
+                period => period,
+            }
+        } else {
+            deadline
+        };
+        (first, deadline, process.pid())
+    }
+}
+
+impl Policy for Deadline {
+    fn name(&self) -> &'static str {
+        if self.rate_monotonic {
+            "RMS"
+        } else {
+            "EDF"
+        }
+    }
+
+    fn queue_configs(&self) -> Vec<QueueConfig> {
+        unlimited_queue_configs(1, 1)
+    }
+
+    fn admit(&mut self, process: &Process, job: &JobConfig, _now: u32) -> usize {
+        self.periods.insert(process.pid(), job.period());
+        0
+    }
+
+    fn pick(&mut self, queues: &[Queue], now: u32) -> Option<(usize, u32)> {
+        let processes = queues
+            .iter()
+            .flat_map(|q| q.processes())
+            .collect::<Vec<&Process>>();
+        let next_release = processes
+            .iter()
+            .map(|p| p.next_schedule_time())
+            .filter(|&t| t > now)
+            .min();
+        self.slice = next_release.map_or(u32::MAX, |t| t - now);
+
+        processes
+            .into_iter()
+            .filter(|p| p.next_schedule_time() <= now)
+            .min_by_key(|p| self.key(p))
+            .map(|p| (0, p.pid()))
+    }
+
+    fn quantum(&self, _process: &Process, _queue: usize) -> u32 {
+        self.slice
+    }
+
+    fn ran(&mut self, _process: &Process, _queue: usize, _run_time: u32, _now: u32) -> usize {
+        0
+    }
+}
@@ -0,0 +1,284 @@
+// lottery.rs
+// Proportional-share scheduling (OSTEP chapter 9): lottery draws a winning ticket at random,
+// stride runs the job with the lowest pass value. Tickets may be issued in a currency,
+// which converts them to base tickets, and a blocked job may transfer its tickets to another.
+// All jobs share a single queue.
+// Author: Hank Bao
+
+use std::collections::BTreeMap;
+
+use super::{unlimited_queue_configs, Policy};
+use crate::config::{JobConfig, QueueConfig};
+use crate::process::Process;
+use crate::queue::Queue;
+use crate::rng::Rng;
+
+// Large number divided by the tickets to get a stride
+const STRIDE1: u64 = 1 << 20;
+
+struct Client {
+    tickets: u32,
+    currency: Option<String>,
+    transfer_to: Option<u32>,
+    // Stride: virtual time, advanced by the stride for every tick run
+    pass: u64,
+    // Not runnable since it last ran, or never ran
+    waiting: bool,
+    cpu: u32,
+}
+
+pub struct Lottery {
+    // None for stride scheduling
+    rng: Option<Rng>,
+    quantum: u32,
+    // Base tickets funding each currency
+    currencies: BTreeMap<String, u32>,
+    clients: BTreeMap<u32, Client>,
+    // CPU time of every job when the first one finished, the window where all of them compete
+    window: Option<(u32, BTreeMap<u32, u32>)>,
+    // Base tickets of the job picked last, including transfers
+    picked_tickets: f64,
+    draws: u32,
+}
+
+impl Lottery {
+    pub fn new(seed: u64, quantum: u32, currencies: &[(String, u32)]) -> Lottery {
+        let mut lottery = Lottery::stride(quantum, currencies);
+        lottery.rng = Some(Rng::new(seed));
+        lottery
+    }
+
+    pub fn stride(quantum: u32, currencies: &[(String, u32)]) -> Lottery {
+        assert!(quantum > 0);
+        Lottery {
+            rng: None,
+            quantum,
+            currencies: currencies.iter().cloned().collect(),
+            clients: BTreeMap::new(),
+            window: None,
+            picked_tickets: 0.0,
+            draws: 0,
+        }
+    }
+
+    // Value of each listed job's tickets in base tickets. A currency is shared among its
+    // active jobs, and a job in an unknown currency is treated as in the base currency.
+    fn base_tickets(&self, active: &[u32]) -> BTreeMap<u32, f64> {
+        let mut issued = BTreeMap::<&str, u32>::new();
+        for pid in active.iter() {
+            let client = &self.clients[pid];
+            if let Some(currency) = client.currency.as_deref() {
+                *issued.entry(currency).or_insert(0) += client.tickets;
+            }
+        }
+
+        active
+            .iter()
+            .map(|pid| {
+                let client = &self.clients[pid];
+                let funding = client
+                    .currency
+                    .as_deref()
+                    .and_then(|c| self.currencies.get(c).map(|&f| (f, issued[c])));
+                let value = match funding {
+                    Some((funding, issued)) if issued > 0 => {
+                        funding as f64 * client.tickets as f64 / issued as f64
+                    }
+                    _ => client.tickets as f64,
+                };
+                (*pid, value)
+            })
+            .collect()
+    }
+
+    // Base tickets of the runnable jobs, including the tickets lent to them by blocked jobs
+    fn funded(&self, queues: &[Queue], now: u32) -> BTreeMap<u32, f64> {
+        let processes = queues
+            .iter()
+            .flat_map(|q| q.processes())
+            .collect::<Vec<&Process>>();
+        let runnable = processes
+            .iter()
+            .filter(|p| p.next_schedule_time() <= now)
+            .map(|p| p.pid())
+            .collect::<Vec<u32>>();
+        let blocked = processes
+            .iter()
+            .filter(|p| p.next_schedule_time() > now && p.start_time() <= now)
+            .map(|p| p.pid())
+            .collect::<Vec<u32>>();
+
+        let all = runnable
+            .iter()
+            .chain(blocked.iter())
+            .copied()
+            .collect::<Vec<u32>>();
+        let values = self.base_tickets(&all);
+
+        let mut funded = runnable
+            .iter()
+            .map(|pid| (*pid, values[pid]))
+            .collect::<BTreeMap<u32, f64>>();
+        for pid in blocked.iter() {
+            if let Some(target) = self.clients[pid].transfer_to {
+                if let Some(value) = funded.get_mut(&target) {
+                    *value += values[pid];
+                }
+            }
+        }
+
+        funded
+    }
+
+    // Charge the run against the tickets the job held when it was picked
+    fn charge(&mut self, pid: u32, run_time: u32) {
+        let stride = (STRIDE1 as f64 / self.picked_tickets.max(1.0)) as u64;
+        let client = self.clients.get_mut(&pid).expect("process not admitted");
+        client.cpu += run_time;
+        client.pass += stride * run_time as u64;
+    }
+}
+
+impl Policy for Lottery {
+    fn name(&self) -> &'static str {
+        if self.rng.is_some() {
+            "lottery"
+        } else {
+            "stride"
+        }
+    }
+
+    fn queue_configs(&self) -> Vec<QueueConfig> {
+        unlimited_queue_configs(1, self.quantum)
+    }
+
+    fn admit(&mut self, process: &Process, job: &JobConfig, _now: u32) -> usize {
+        let client = Client {
+            tickets: job.tickets(),
+            currency: job.currency().map(String::from),
+            transfer_to: job.transfer_to(),
+            pass: 0,
+            waiting: true,
+            cpu: 0,
+        };
+        self.clients.insert(process.pid(), client);
+        0
+    }
+
+    fn pick(&mut self, queues: &[Queue], now: u32) -> Option<(usize, u32)> {
+        let funded = self.funded(queues, now);
+        if funded.is_empty() {
+            return None;
+        }
+
+        // A job that was not competing must not catch up on the passes it missed
+        let lowest = funded
+            .keys()
+            .filter(|pid| !self.clients[pid].waiting)
+            .map(|pid| self.clients[pid].pass)
+            .min();
+        for pid in funded.keys() {
+            let client = self.clients.get_mut(pid).unwrap();
+            if client.waiting {
+                client.pass = client.pass.max(lowest.unwrap_or(0));
+                client.waiting = false;
+            }
+        }
+
+        let pid = match self.rng.as_mut() {
+            Some(rng) => {
+                // Draw a winning ticket among all funded tickets, in units of 1/100 ticket
+                let total = funded.values().sum::<f64>();
+                let scaled = ((total * 100.0) as u32).max(1);
+                let mut winner = rng.below(scaled) as f64 / 100.0;
+                self.draws += 1;
+                let mut chosen = *funded.keys().last().unwrap();
+                for (&pid, &value) in funded.iter() {
+                    if winner < value {
+                        chosen = pid;
+                        break;
+                    }
+                    winner -= value;
+                }
+                chosen
+            }
+            None => *funded
+                .keys()
+                .min_by_key(|pid| self.clients[pid].pass)
+                .unwrap(),
+        };
+
+        self.picked_tickets = funded[&pid];
+        Some((0, pid))
+    }
+
+    fn quantum(&self, _process: &Process, _queue: usize) -> u32 {
+        self.quantum
+    }
+
+    fn ran(&mut self, process: &Process, _queue: usize, run_time: u32, _now: u32) -> usize {
+        self.charge(process.pid(), run_time);
+        if process.is_blocked() {
+            self.clients.get_mut(&process.pid()).unwrap().waiting = true;
+        }
+        0
+    }
+
+    fn finished(&mut self, process: &Process, now: u32) {
+        let pid = process.pid();
+        let run_time = process.work_done() - self.clients[&pid].cpu;
+        self.charge(pid, run_time);
+
+        if self.window.is_none() {
+            let cpu = self
+                .clients
+                .iter()
+                .map(|(&pid, c)| (pid, c.cpu))
+                .collect::<BTreeMap<u32, u32>>();
+            self.window = Some((now, cpu));
+        }
+    }
+
+    fn report(&self) {
+        let (end, cpu) = match self.window.as_ref() {
+            Some(window) => window,
+            None => return,
+        };
+
+        // Target shares assume every job in the window competes, with no transfers. Jobs
+        // admitted later, e.g. spawned children, did not compete.
+        let pids = cpu.keys().copied().collect::<Vec<u32>>();
+        let values = self.base_tickets(&pids);
+        let total_tickets = values.values().sum::<f64>();
+        let total_cpu = cpu.values().sum::<u32>().max(1);
+
+        if self.rng.is_some() {
+            println!("Lottery draws: {}.", self.draws);
+        }
+        println!("CPU share until the first job finished at {}:", end);
+        for pid in pids.iter() {
+            let client = &self.clients[pid];
+            println!(
+                "Process {} tickets {}{}, target {:.1}%, achieved {:.1}%.",
+                pid,
+                client.tickets,
+                client
+                    .currency
+                    .as_deref()
+                    .map_or(String::new(), |c| format!(" in {}", c)),
+                values[pid] * 100.0 / total_tickets,
+                cpu[pid] as f64 * 100.0 / total_cpu as f64
+            );
+        }
+
+        let later = self
+            .clients
+            .keys()
+            .filter(|pid| !cpu.contains_key(pid))
+            .map(|pid| pid.to_string())
+            .collect::<Vec<String>>();
+        if !later.is_empty() {
+            println!("Admitted after {}: {}.", end, later.join(", "));
+        }
+    }
+}
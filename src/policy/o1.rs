@@ -0,0 +1,315 @@
+// o1.rs
+// Linux 2.6 O(1) scheduler: 140 priority lists split into an active and an expired array,
+// timeslices scaled by static priority, and an interactivity bonus from the sleep average.
+// Every priority is a queue, but jobs only get the 40 normal priorities (100 to 139).
+// Author: Hank Bao
+
+use std::collections::{BTreeMap, VecDeque};
+
+use super::{unlimited_queue_configs, Policy};
+use crate::config::{JobConfig, QueueConfig};
+use crate::process::Process;
+use crate::queue::Queue;
+
+const MAX_RT_PRIO: i32 = 100;
+const MAX_PRIO: i32 = 140;
+const MAX_USER_PRIO: i32 = MAX_PRIO - MAX_RT_PRIO;
+const MAX_BONUS: i32 = 10;
+const INTERACTIVE_DELTA: i32 = 2;
+// Times in ticks of one millisecond
+const DEF_TIMESLICE: u32 = 100;
+const MIN_TIMESLICE: u32 = 5;
+const MAX_SLEEP_AVG: u32 = 1000;
+const STARVATION_LIMIT: u32 = MAX_SLEEP_AVG;
+const BITMAP_SIZE: usize = (MAX_PRIO as usize).div_ceil(64);
+
+struct Task {
+    static_prio: i32,
+    sleep_avg: u32,
+    time_slice: u32,
+    expirations: u32,
+    // The array and priority list the task is queued in, None while it sleeps
+    queued: Option<(usize, usize)>,
+}
+
+impl Task {
+    fn nice(&self) -> i32 {
+        self.static_prio - 120
+    }
+
+    // task_timeslice: nice -20 gets 800ms, nice 0 gets 100ms and nice 19 gets 5ms
+    fn timeslice(&self) -> u32 {
+        let base = if self.static_prio < 120 {
+            DEF_TIMESLICE * 4
+        } else {
+            DEF_TIMESLICE
+        };
+        (base * (MAX_PRIO - self.static_prio) as u32 / (MAX_USER_PRIO / 2) as u32)
+            .max(MIN_TIMESLICE)
+    }
+
+    // effective_prio: up to 5 levels better or worse than the static priority
+    fn prio(&self) -> i32 {
+        let bonus = (self.sleep_avg * MAX_BONUS as u32 / MAX_SLEEP_AVG) as i32;
+        (self.static_prio - bonus + MAX_BONUS / 2).clamp(MAX_RT_PRIO, MAX_PRIO - 1)
+    }
+
+    fn interactive(&self) -> bool {
+        let delta = self.nice() * MAX_BONUS / 40 + INTERACTIVE_DELTA;
+        self.prio() <= self.static_prio - delta
+    }
+
+    fn queue(&self) -> usize {
+        self.prio() as usize
+    }
+}
+
+// struct prio_array: a list of tasks per priority and a bitmap of the lists that are not empty
+struct PrioArray {
+    nr_active: u32,
+    bitmap: [u64; BITMAP_SIZE],
+    queue: Vec<VecDeque<u32>>,
+}
+
+impl Default for PrioArray {
+    fn default() -> Self {
+        PrioArray {
+            nr_active: 0,
+            bitmap: [0; BITMAP_SIZE],
+            queue: vec![VecDeque::new(); MAX_PRIO as usize],
+        }
+    }
+}
+
+impl PrioArray {
+    fn enqueue(&mut self, pid: u32, prio: usize) {
+        self.queue[prio].push_back(pid);
+        self.bitmap[prio / 64] |= 1 << (prio % 64);
+        self.nr_active += 1;
+    }
+
+    // A task that was preempted before its slice ran out keeps its place at the head
+    fn enqueue_head(&mut self, pid: u32, prio: usize) {
+        self.queue[prio].push_front(pid);
+        self.bitmap[prio / 64] |= 1 << (prio % 64);
+        self.nr_active += 1;
+    }
+
+    fn dequeue(&mut self, pid: u32, prio: usize) {
+        if let Some(index) = self.queue[prio].iter().position(|&p| p == pid) {
+            self.queue[prio].remove(index);
+            self.nr_active -= 1;
+        }
+        if self.queue[prio].is_empty() {
+            self.bitmap[prio / 64] &= !(1 << (prio % 64));
+        }
+    }
+
+    // sched_find_first_bit: the first task of the highest priority list that is not empty
+    fn first(&self) -> Option<(usize, u32)> {
+        let word = self.bitmap.iter().position(|&w| w != 0)?;
+        let prio = word * 64 + self.bitmap[word].trailing_zeros() as usize;
+        self.queue[prio].front().map(|&pid| (prio, pid))
+    }
+}
+
+#[derive(Default)]
+pub struct O1 {
+    tasks: BTreeMap<u32, Task>,
+    arrays: [PrioArray; 2],
+    // Index of the active array, the other one is the expired array
+    active: usize,
+    // Tasks waiting for I/O or their arrival as (wake up time, pid), they are in neither array
+    sleeping: Vec<(u32, u32)>,
+    // When the first task expired since the last array switch
+    expired_timestamp: Option<u32>,
+    // Tasks that have not finished yet
+    nr_running: u32,
+    switches: u32,
+}
+
+impl O1 {
+    pub fn new() -> O1 {
+        O1::default()
+    }
+
+    // EXPIRED_STARVING: the expired array waited too long, so interactive tasks expire as well
+    fn expired_starving(&self, now: u32) -> bool {
+        match self.expired_timestamp {
+            Some(since) => now - since > STARVATION_LIMIT * self.nr_running,
+            None => false,
+        }
+    }
+
+    fn enqueue(&mut self, pid: u32, array: usize, head: bool) {
+        let task = self.tasks.get_mut(&pid).expect("process not admitted");
+        let prio = task.queue();
+        task.queued = Some((array, prio));
+        if head {
+            self.arrays[array].enqueue_head(pid, prio);
+        } else {
+            self.arrays[array].enqueue(pid, prio);
+        }
+    }
+
+    fn dequeue(&mut self, pid: u32) {
+        if let Some((array, prio)) = self.tasks.get_mut(&pid).and_then(|t| t.queued.take()) {
+            self.arrays[array].dequeue(pid, prio);
+        }
+    }
+
+    // activate_task: tasks that woke up join the tail of the active array
+    fn wake_up(&mut self, now: u32) {
+        self.sleeping.sort_unstable();
+        let woken = self.sleeping.partition_point(|&(wake, _)| wake <= now);
+        for (_, pid) in self.sleeping.drain(..woken).collect::<Vec<(u32, u32)>>() {
+            self.enqueue(pid, self.active, false);
+        }
+    }
+
+    // Jobs that an event moved out of the queues and back, e.g. a suspended job that was
+    // resumed, are in no array any more. They are taken back once the arrays run empty.
+    fn adopt(&mut self, queues: &[Queue], now: u32) {
+        let lost = queues
+            .iter()
+            .flat_map(|q| q.processes())
+            .filter(|p| p.next_schedule_time() <= now)
+            .map(|p| p.pid())
+            .filter(|pid| {
+                self.tasks.get(pid).is_some_and(|t| t.queued.is_none())
+                    && self.sleeping.iter().all(|&(_, p)| p != *pid)
+            })
+            .collect::<Vec<u32>>();
+        for pid in lost {
+            self.enqueue(pid, self.active, false);
+        }
+    }
+}
+
+impl Policy for O1 {
+    fn name(&self) -> &'static str {
+        "O(1)"
+    }
+
+    fn queue_configs(&self) -> Vec<QueueConfig> {
+        unlimited_queue_configs(MAX_PRIO as usize, DEF_TIMESLICE)
+    }
+
+    fn admit(&mut self, process: &Process, job: &JobConfig, _now: u32) -> usize {
+        let mut task = Task {
+            static_prio: 120 + job.nice(),
+            sleep_avg: 0,
+            time_slice: 0,
+            expirations: 0,
+            queued: None,
+        };
+        task.time_slice = task.timeslice();
+
+        let queue = task.queue();
+        self.tasks.insert(process.pid(), task);
+        self.nr_running += 1;
+        self.sleeping
+            .push((process.next_schedule_time(), process.pid()));
+        queue
+    }
+
+    // Run the first task of the highest priority list of the active array,
+    // switching arrays when the active one is empty
+    fn pick(&mut self, queues: &[Queue], now: u32) -> Option<(usize, u32)> {
+        self.wake_up(now);
+
+        loop {
+            if self.arrays[self.active].nr_active == 0 {
+                if self.arrays[1 - self.active].nr_active > 0 {
+                    self.active = 1 - self.active;
+                    self.expired_timestamp = None;
+                    self.switches += 1;
+                } else {
+                    self.adopt(queues, now);
+                }
+            }
+            let (prio, pid) = self.arrays[self.active].first()?;
+
+            // The task is normally in the queue of its priority, unless an event moved it
+            let queue = if queues[prio].processes().any(|p| p.pid() == pid) {
+                Some(prio)
+            } else {
+                queues
+                    .iter()
+                    .position(|q| q.processes().any(|p| p.pid() == pid))
+            };
+            match queue {
+                Some(queue) => return Some((queue, pid)),
+                // Killed or suspended by an event
+                None => self.dequeue(pid),
+            }
+        }
+    }
+
+    fn quantum(&self, process: &Process, _queue: usize) -> u32 {
+        self.tasks[&process.pid()].time_slice
+    }
+
+    fn ran(&mut self, process: &Process, _queue: usize, run_time: u32, now: u32) -> usize {
+        let starving = self.expired_starving(now);
+        let task = self
+            .tasks
+            .get_mut(&process.pid())
+            .expect("process not admitted");
+
+        task.sleep_avg = task.sleep_avg.saturating_sub(run_time);
+        task.time_slice = task.time_slice.saturating_sub(run_time);
+
+        if process.is_blocked() {
+            // A sleeping task leaves the arrays and wakes up into the active array,
+            // credited with the time it slept. It keeps what is left of its slice.
+            task.sleep_avg = (task.sleep_avg + process.io_length()).min(MAX_SLEEP_AVG);
+            if task.time_slice == 0 {
+                task.time_slice = task.timeslice();
+            }
+            let queue = task.queue();
+            self.dequeue(process.pid());
+            self.sleeping
+                .push((process.next_schedule_time(), process.pid()));
+            return queue;
+        }
+
+        let (array, head) = if task.time_slice == 0 {
+            task.time_slice = task.timeslice();
+
+            // Interactive tasks go back into the active array unless the expired array starves
+            if !task.interactive() || starving {
+                task.expirations += 1;
+                self.expired_timestamp.get_or_insert(now);
+                (1 - self.active, false)
+            } else {
+                (self.active, false)
+            }
+        } else {
+            (self.active, true)
+        };
+        let queue = task.queue();
+        self.dequeue(process.pid());
+        self.enqueue(process.pid(), array, head);
+        queue
+    }
+
+    fn finished(&mut self, process: &Process, _now: u32) {
+        // The task is kept for the report
+        self.dequeue(process.pid());
+        self.nr_running -= 1;
+    }
+
+    fn report(&self) {
+        println!("Array switches: {}.", self.switches);
+        for (pid, task) in self.tasks.iter() {
+            println!(
+                "Process {} static priority {}, timeslice {}, expired {} times.",
+                pid,
+                task.static_prio,
+                task.timeslice(),
+                task.expirations
+            );
+        }
+    }
+}
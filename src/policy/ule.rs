@@ -0,0 +1,218 @@
+// ule.rs
+// FreeBSD ULE style scheduler: jobs are scored by their sleep/run ratio, interactive jobs
+// go to a real-time like band above the batch band, where priority follows CPU usage and nice.
+// Author: Hank Bao
+
+use std::collections::BTreeMap;
+
+use super::{unlimited_queue_configs, Policy};
+use crate::config::{JobConfig, QueueConfig};
+use crate::process::Process;
+
+const INTERACT_MAX: u32 = 100;
+const INTERACT_HALF: u32 = INTERACT_MAX / 2;
+// Queues of the interactive band, ordered by score
+const INTERACT_LEVELS: usize = 8;
+// Queues of the batch band, ordered by CPU usage and nice
+const BATCH_LEVELS: usize = 16;
+
+struct UleState {
+    runtime: u32,
+    sleeptime: u32,
+    nice: i32,
+    interactive: bool,
+}
+
+impl UleState {
+    // sched_interact_score: 0 is the most interactive, INTERACT_MAX the most CPU bound
+    fn score(&self) -> u32 {
+        if self.runtime > self.sleeptime {
+            let div = (self.runtime / INTERACT_HALF).max(1);
+            INTERACT_HALF + (INTERACT_HALF - self.sleeptime / div)
+        } else if self.sleeptime > self.runtime {
+            let div = (self.sleeptime / INTERACT_HALF).max(1);
+            self.runtime / div
+        } else if self.runtime > 0 {
+            INTERACT_HALF
+        } else {
+            0
+        }
+    }
+
+    // Share of the recent history spent running, from 0 to 100
+    fn cpu_percent(&self) -> u32 {
+        let total = self.runtime + self.sleeptime;
+        if total == 0 {
+            0
+        } else {
+            (self.runtime as u64 * 100 / total as u64) as u32
+        }
+    }
+}
+
+pub struct Ule {
+    threshold: u32,
+    slice: u32,
+    // sched_slp_run_max, the amount of history kept before it is scaled down
+    history: u32,
+    states: BTreeMap<u32, UleState>,
+    // (time, pid, interactive, score) whenever a job is classified differently
+    transitions: Vec<(u32, u32, bool, u32)>,
+    finish_times: BTreeMap<u32, u32>,
+}
+
+impl Ule {
+    pub const DEFAULT_THRESHOLD: u32 = 30;
+    // A 10 tick slice and 5 seconds of history at one tick per millisecond
+    pub const DEFAULT_SLICE: u32 = 10;
+    pub const DEFAULT_HISTORY: u32 = 5000;
+
+    pub fn new(threshold: u32, slice: u32, history: u32) -> Ule {
+        assert!(slice > 0 && history > 0);
+        Ule {
+            threshold,
+            slice,
+            history,
+            states: BTreeMap::new(),
+            transitions: Vec::new(),
+            finish_times: BTreeMap::new(),
+        }
+    }
+
+    // The default slice and history with the given interactivity threshold
+    pub fn with_threshold(threshold: u32) -> Ule {
+        Ule::new(threshold, Self::DEFAULT_SLICE, Self::DEFAULT_HISTORY)
+    }
+
+    fn queue(&self, state: &UleState) -> usize {
+        let score = state.score();
+        if state.interactive {
+            (score as usize * INTERACT_LEVELS / self.threshold.max(1) as usize)
+                .min(INTERACT_LEVELS - 1)
+        } else {
+            // Three quarters of the band follow CPU usage, the last quarter follows nice
+            let usage = state.cpu_percent() as usize * (BATCH_LEVELS * 3 / 4 - 1) / 100;
+            let nice = (state.nice + 20) as usize * (BATCH_LEVELS / 4) / 40;
+            INTERACT_LEVELS + (usage + nice).min(BATCH_LEVELS - 1)
+        }
+    }
+
+    fn classify(&mut self, pid: u32, now: u32) -> usize {
+        let threshold = self.threshold;
+        let state = self.states.get_mut(&pid).expect("process not admitted");
+
+        // sched_interact_update: keep a bounded history so old behavior fades
+        let total = state.runtime + state.sleeptime;
+        if total > self.history {
+            state.runtime = (state.runtime as u64 * self.history as u64 / total as u64) as u32;
+            state.sleeptime = (state.sleeptime as u64 * self.history as u64 / total as u64) as u32;
+        }
+
+        let score = state.score();
+        let interactive = score < threshold;
+        if interactive != state.interactive {
+            state.interactive = interactive;
+            self.transitions.push((now, pid, interactive, score));
+        }
+
+        let state = &self.states[&pid];
+        self.queue(state)
+    }
+}
+
+impl Default for Ule {
+    fn default() -> Self {
+        Ule::with_threshold(Self::DEFAULT_THRESHOLD)
+    }
+}
+
+impl Policy for Ule {
+    fn name(&self) -> &'static str {
+        "ULE"
+    }
+
+    fn queue_configs(&self) -> Vec<QueueConfig> {
+        unlimited_queue_configs(INTERACT_LEVELS + BATCH_LEVELS, self.slice)
+    }
+
+    // A new job has no history and scores 0, so it starts interactive
+    fn admit(&mut self, process: &Process, job: &JobConfig, now: u32) -> usize {
+        let state = UleState {
+            runtime: 0,
+            sleeptime: 0,
+            nice: job.nice(),
+            interactive: true,
+        };
+        self.states.insert(process.pid(), state);
+        self.transitions
+            .push((now.max(process.start_time()), process.pid(), true, 0));
+
+        self.classify(process.pid(), now)
+    }
+
+    fn quantum(&self, _process: &Process, _queue: usize) -> u32 {
+        self.slice
+    }
+
+    // Charge the run, and the sleep the job is about to do if it blocked on I/O
+    fn ran(&mut self, process: &Process, _queue: usize, run_time: u32, now: u32) -> usize {
+        let state = self
+            .states
+            .get_mut(&process.pid())
+            .expect("process not admitted");
+        state.runtime += run_time;
+        if process.is_blocked() {
+            state.sleeptime += process.io_length();
+        }
+
+        self.classify(process.pid(), now)
+    }
+
+    fn finished(&mut self, process: &Process, now: u32) {
+        self.states.remove(&process.pid());
+        self.finish_times.insert(process.pid(), now);
+    }
+
+    fn report(&self) {
+        println!(
+            "Interactivity classification (threshold {}):",
+            self.threshold
+        );
+
+        for (&pid, &finish_time) in self.finish_times.iter() {
+            let transitions = self
+                .transitions
+                .iter()
+                .filter(|t| t.1 == pid)
+                .collect::<Vec<_>>();
+
+            // Each transition holds until the next one, the last one until the job finished
+            let interactive_time = transitions
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.2)
+                .map(|(i, t)| transitions.get(i + 1).map_or(finish_time, |next| next.0) - t.0)
+                .sum::<u32>();
+
+            let history = transitions
+                .iter()
+                .map(|&&(time, _, interactive, score)| {
+                    format!(
+                        "{}: {} (score {})",
+                        time,
+                        if interactive { "interactive" } else { "batch" },
+                        score
+                    )
+                })
+                .collect::<Vec<String>>();
+
+            println!(
+                "Process {} interactive for {} of {} ticks. {}.",
+                pid,
+                interactive_time,
+                finish_time - transitions.first().map_or(finish_time, |t| t.0),
+                history.join(", ")
+            );
+        }
+    }
+}
@@ -2,6 +2,11 @@
 // Process struct as the process control block
 // Author: Hank Bao
 
+use std::fmt;
+
+use crate::config::SchedClass;
+use crate::snapshot::{self, Fields};
+
 pub struct Process {
     pid: u32,
     io_interval: u32,
@@ -13,7 +18,15 @@ pub struct Process {
     turnaround_time: u32,
     response_time: u32,
     allotment: u32,
+    longest_wait: u32,
+    wait_reset: u32,
+    promotions: u32,
+    tickets: u32,
+    class: SchedClass,
+    rt_priority: u32,
+    deadline: Option<u32>,
     state: ProcessState,
+    trace: bool,
 }
 
 #[allow(dead_code)]
@@ -36,7 +49,15 @@ impl Process {
             turnaround_time: 0,
             response_time: 0,
             allotment: 0,
+            longest_wait: 0,
+            wait_reset: 0,
+            promotions: 0,
+            tickets: 1,
+            class: SchedClass::Other,
+            rt_priority: 0,
+            deadline: None,
             state: ProcessState::Ready,
+            trace: true,
         }
     }
 
@@ -84,43 +105,179 @@ impl Process {
         self.allotment
     }
 
-    pub fn is_blocked(&self) -> bool {
-        match self.state {
-            ProcessState::Blocked => true,
-            _ => false,
+    // The longest time the process has been schedulable without being run
+    pub fn longest_wait(&self) -> u32 {
+        self.longest_wait
+    }
+
+    // How long the process has been schedulable without running at the given time
+    pub fn waited(&self, now: u32) -> u32 {
+        now.saturating_sub(self.next_schedule_time.max(self.wait_reset))
+    }
+
+    // Start counting the wait from the given time, e.g. after the process has been aged
+    pub fn reset_wait(&mut self, now: u32) {
+        self.wait_reset = now;
+    }
+
+    // The process cannot be scheduled before the given time, e.g. after it was suspended.
+    // Its wait only counts from then.
+    pub fn delay_until(&mut self, time: u32) {
+        self.next_schedule_time = self.next_schedule_time.max(time);
+    }
+
+    // Number of times the process was promoted by aging
+    pub fn promotions(&self) -> u32 {
+        self.promotions
+    }
+
+    pub fn add_promotion(&mut self) {
+        self.promotions += 1;
+    }
+
+    // Weight of the process in a lottery queue
+    pub fn set_tickets(&mut self, tickets: u32) {
+        self.tickets = tickets;
+    }
+
+    pub fn tickets(&self) -> u32 {
+        self.tickets
+    }
+
+    pub fn set_class(&mut self, class: SchedClass, rt_priority: u32) {
+        self.class = class;
+        self.rt_priority = rt_priority;
+    }
+
+    pub fn class(&self) -> SchedClass {
+        self.class
+    }
+
+    pub fn rt_priority(&self) -> u32 {
+        self.rt_priority
+    }
+
+    // Absolute time by which the process should finish
+    pub fn set_deadline(&mut self, deadline: Option<u32>) {
+        self.deadline = deadline;
+    }
+
+    pub fn deadline(&self) -> Option<u32> {
+        self.deadline
+    }
+
+    // How late the process finished, negative if early. None without a deadline or before it finished.
+    pub fn lateness(&self) -> Option<i64> {
+        if !self.is_finished() {
+            return None;
         }
+
+        let finish_time = self.start_time + self.turnaround_time;
+        self.deadline.map(|d| finish_time as i64 - d as i64)
+    }
+
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    pub fn state(&self) -> ProcessState {
+        self.state
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        matches!(self.state, ProcessState::Blocked)
     }
 
     pub fn is_finished(&self) -> bool {
-        match self.state {
-            ProcessState::Finished => true,
-            _ => false,
+        matches!(self.state, ProcessState::Finished)
+    }
+
+    // The fields of a "process" snapshot line
+    pub fn snapshot(&self) -> String {
+        format!(
+            "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+            self.pid,
+            self.io_interval,
+            self.io_length,
+            self.workload,
+            self.work_done,
+            self.start_time,
+            self.next_schedule_time,
+            self.turnaround_time,
+            self.response_time,
+            self.allotment,
+            self.longest_wait,
+            self.wait_reset,
+            self.promotions,
+            self.tickets,
+            snapshot::name(self.class),
+            self.rt_priority,
+            snapshot::opt(self.deadline),
+            self.state.name(),
+            self.trace
+        )
+    }
+
+    pub fn from_snapshot(fields: &mut Fields) -> Option<Process> {
+        let process = Process {
+            pid: fields.u32()?,
+            io_interval: fields.u32()?,
+            io_length: fields.u32()?,
+            workload: fields.u32()?,
+            work_done: fields.u32()?,
+            start_time: fields.u32()?,
+            next_schedule_time: fields.u32()?,
+            turnaround_time: fields.u32()?,
+            response_time: fields.u32()?,
+            allotment: fields.u32()?,
+            longest_wait: fields.u32()?,
+            wait_reset: fields.u32()?,
+            promotions: fields.u32()?,
+            tickets: fields.u32()?,
+            class: fields.value()?,
+            rt_priority: fields.u32()?,
+            deadline: fields.opt_u32()?,
+            state: ProcessState::from_name(fields.word()?)?,
+            trace: fields.bool()?,
+        };
+        fields.end()?;
+
+        if process.work_done > process.workload {
+            return None;
         }
+        Some(process)
     }
 
-    pub fn run(&mut self, quantum: u32, at: u32, queue: usize) -> u32 {
+    pub fn run(&mut self, quantum: u32, at: u32, level: Level) -> u32 {
         // record the response time
         if self.response_time == 0 {
             assert!(at >= self.start_time);
             self.response_time = at - self.start_time;
         }
 
+        // record how long the process waited since it became schedulable
+        assert!(at >= self.next_schedule_time);
+        let waited = at - self.next_schedule_time;
+        self.longest_wait = self.longest_wait.max(waited);
+
         match self.state {
-            ProcessState::Ready => self.run_from_ready(quantum, at, queue),
-            ProcessState::Running => self.run_from_running(quantum, at, queue),
-            ProcessState::Blocked => self.run_from_blocked(quantum, at, queue),
+            ProcessState::Ready => self.run_from_ready(quantum, at, level),
+            ProcessState::Running => self.run_from_running(quantum, at, level),
+            ProcessState::Blocked => self.run_from_blocked(quantum, at, level),
             ProcessState::Finished => panic!("Run a finished process {}.", self.pid),
         }
     }
 
-    fn run_from_ready(&mut self, quantum: u32, at: u32, queue: usize) -> u32 {
+    fn run_from_ready(&mut self, quantum: u32, at: u32, level: Level) -> u32 {
         self.state = ProcessState::Running;
-        println!("[{}:<{}>] Process {} start running.", at, queue, self.pid);
+        if self.trace {
+            println!("[{}:<{}>] Process {} start running.", at, level, self.pid);
+        }
 
-        self.run_from_running(quantum, at, queue)
+        self.run_from_running(quantum, at, level)
     }
 
-    fn run_from_running(&mut self, quantum: u32, at: u32, queue: usize) -> u32 {
+    fn run_from_running(&mut self, quantum: u32, at: u32, level: Level) -> u32 {
         assert_eq!(self.state, ProcessState::Running);
         assert!(self.allotment > 0);
 
@@ -172,12 +329,16 @@ impl Process {
         }
 
         // Print status
+        if !self.trace {
+            return run_time;
+        }
+
         match self.state {
             ProcessState::Running => {
                 println!(
                     "[{}:<{}>] Process {} has run for {}.",
                     at + run_time,
-                    queue,
+                    level,
                     self.pid,
                     run_time
                 )
@@ -185,7 +346,7 @@ impl Process {
             ProcessState::Blocked => println!(
                 "[{}:<{}>] Process {} has run for {}, then blocked. It will perform I/O for {}",
                 at + run_time,
-                queue,
+                level,
                 self.pid,
                 run_time,
                 self.io_length
@@ -193,7 +354,7 @@ impl Process {
             ProcessState::Finished => println!(
                 "[{}:<{}>] Process {} has run for {}, then finished.",
                 at + run_time,
-                queue,
+                level,
                 self.pid,
                 run_time
             ),
@@ -203,21 +364,65 @@ impl Process {
         run_time
     }
 
-    fn run_from_blocked(&mut self, quantum: u32, at: u32, queue: usize) -> u32 {
+    fn run_from_blocked(&mut self, quantum: u32, at: u32, level: Level) -> u32 {
         self.state = ProcessState::Running;
-        println!(
-            "[{}:<{}>] Process {} resume running from I/O.",
-            at, queue, self.pid
-        );
+        if self.trace {
+            println!(
+                "[{}:<{}>] Process {} resume running from I/O.",
+                at, level, self.pid
+            );
+        }
+
+        self.run_from_running(quantum, at, level)
+    }
+}
 
-        self.run_from_running(quantum, at, queue)
+// Where a process runs, shown in the trace with its class, e.g. OTHER:2 for time-sharing
+// queue 2 or FIFO:50 for SCHED_FIFO priority 50
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Level {
+    Queue(usize),
+    Fifo(u32),
+    Rr(u32),
+    Idle,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Queue(index) => write!(f, "OTHER:{}", index),
+            Level::Fifo(priority) => write!(f, "FIFO:{}", priority),
+            Level::Rr(priority) => write!(f, "RR:{}", priority),
+            Level::Idle => write!(f, "IDLE"),
+        }
     }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-enum ProcessState {
+pub enum ProcessState {
     Ready,
     Running,
     Blocked,
     Finished,
 }
+
+impl ProcessState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProcessState::Ready => "ready",
+            ProcessState::Running => "running",
+            ProcessState::Blocked => "blocked",
+            ProcessState::Finished => "finished",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ProcessState> {
+        match name {
+            "ready" => Some(ProcessState::Ready),
+            "running" => Some(ProcessState::Running),
+            "blocked" => Some(ProcessState::Blocked),
+            "finished" => Some(ProcessState::Finished),
+            _ => None,
+        }
+    }
+}
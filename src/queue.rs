@@ -2,13 +2,19 @@
 // Queue struct and implementation.
 // Author: Hank Bao
 
-use crate::config::QueueConfig;
+use crate::config::{Discipline, QueueConfig};
 use crate::process::Process;
+use crate::rng::Rng;
+use crate::snapshot::{self, Fields};
 
 pub struct Queue {
     quantum: u32,
     allotment: u32,
     push_front: bool,
+    aging_threshold: u32,
+    aging_target: Option<usize>,
+    discipline: Discipline,
+    rng: Rng,
     processes: Vec<Process>,
 }
 
@@ -18,24 +24,101 @@ impl Queue {
             quantum,
             allotment,
             push_front,
+            aging_threshold: 0,
+            aging_target: None,
+            discipline: Discipline::RoundRobin,
+            rng: Rng::new(0),
             processes: Vec::new(),
         }
     }
 
+    pub fn set_aging(&mut self, threshold: u32, target: Option<usize>) {
+        self.aging_threshold = threshold;
+        self.aging_target = target;
+    }
+
+    pub fn set_discipline(&mut self, discipline: Discipline) {
+        self.discipline = discipline;
+    }
+
+    // Seed the draws of a lottery queue
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn set_quantum(&mut self, quantum: u32) {
+        self.quantum = quantum;
+    }
+
+    pub fn discipline(&self) -> Discipline {
+        self.discipline
+    }
+
+    pub fn aging_threshold(&self) -> u32 {
+        self.aging_threshold
+    }
+
+    pub fn aging_target(&self) -> Option<usize> {
+        self.aging_target
+    }
+
     pub fn quantum(&self) -> u32 {
         self.quantum
     }
 
+    // The time a picked process may run, an FCFS queue runs it until it blocks or finishes
+    pub fn time_slice(&self) -> u32 {
+        match self.discipline {
+            Discipline::Fcfs => u32::MAX,
+            _ => self.quantum,
+        }
+    }
+
     pub fn allotment(&self) -> u32 {
         self.allotment
     }
 
+    pub fn push_front(&self) -> bool {
+        self.push_front
+    }
+
+    pub fn set_trace(&mut self, trace: bool) {
+        for process in self.processes.iter_mut() {
+            process.set_trace(trace);
+        }
+    }
+
+    // The processes in this queue, in scheduling order
+    pub fn processes(&self) -> impl Iterator<Item = &Process> {
+        self.processes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.processes.len()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.processes.is_empty()
     }
 
     pub fn pop_all(&mut self) -> Vec<Process> {
-        std::mem::replace(&mut self.processes, Vec::new())
+        std::mem::take(&mut self.processes)
+    }
+
+    pub fn take_process(&mut self, pid: u32) -> Option<Process> {
+        self.processes
+            .iter()
+            .position(|p| p.pid() == pid)
+            .map(|i| self.processes.remove(i))
+    }
+
+    // Remove and return the processes matching the predicate, keeping the order of the rest
+    pub fn take_processes(&mut self, pred: impl Fn(&Process) -> bool) -> Vec<Process> {
+        let (taken, kept) = std::mem::take(&mut self.processes)
+            .into_iter()
+            .partition(|p| pred(p));
+        self.processes = kept;
+        taken
     }
 
     pub fn add_process(&mut self, mut process: Process) {
@@ -55,17 +138,110 @@ impl Queue {
             .any(|p| p.next_schedule_time() <= current_time)
     }
 
+    // Take the process to run next according to the discipline of the queue
     pub fn take_next_schedulable_process(&mut self, current_time: u32) -> Option<Process> {
-        self.processes
+        let mut schedulable = self
+            .processes
             .iter()
-            .position(|p| p.next_schedule_time() <= current_time)
-            .map(|i| {
-                let process = self.processes.remove(i);
-                process
-            })
+            .enumerate()
+            .filter(|(_, p)| p.next_schedule_time() <= current_time);
+
+        let position = match self.discipline {
+            Discipline::RoundRobin => schedulable.next().map(|(i, _)| i),
+            Discipline::Fcfs => schedulable
+                .min_by_key(|(_, p)| p.start_time())
+                .map(|(i, _)| i),
+            Discipline::Srt => schedulable
+                .min_by_key(|(_, p)| p.workload() - p.work_done())
+                .map(|(i, _)| i),
+            Discipline::Lottery => {
+                let candidates = schedulable
+                    .map(|(i, p)| (i, p.tickets()))
+                    .collect::<Vec<(usize, u32)>>();
+                let total = candidates.iter().map(|&(_, t)| t).sum::<u32>();
+                if total == 0 {
+                    None
+                } else {
+                    let mut winner = self.rng.below(total);
+                    candidates
+                        .into_iter()
+                        .find(|&(_, tickets)| {
+                            if winner < tickets {
+                                true
+                            } else {
+                                winner -= tickets;
+                                false
+                            }
+                        })
+                        .map(|(i, _)| i)
+                }
+            }
+        };
+
+        position.map(|i| self.processes.remove(i))
     }
 
-    pub fn put_process_back(&mut self, process: Process, bump: bool) {
+    // The settings of the queue, as it would be created from them
+    pub fn config(&self) -> QueueConfig {
+        let mut config = QueueConfig::new(self.quantum, self.allotment, self.push_front);
+        config.set_aging(self.aging_threshold, self.aging_target);
+        config.set_discipline(self.discipline);
+        config
+    }
+
+    // Change the settings in place, keeping the processes and the state of the lottery draws.
+    // A process keeps what is left of its allotment, up to the new allotment.
+    pub fn reconfigure(&mut self, config: QueueConfig) {
+        self.quantum = config.quantum();
+        self.allotment = config.allotment();
+        self.push_front = config.push_front();
+        self.aging_threshold = config.aging_threshold();
+        self.aging_target = config.aging_target();
+        self.discipline = config.discipline();
+
+        for process in self.processes.iter_mut() {
+            if process.allotment() > self.allotment {
+                process.set_allotment(self.allotment);
+            }
+        }
+    }
+
+    // The fields of a "queue" snapshot line, its processes follow on their own lines
+    pub fn snapshot(&self) -> String {
+        format!(
+            "{} {} {} {} {} {} {}",
+            self.quantum,
+            self.allotment,
+            self.push_front,
+            self.aging_threshold,
+            snapshot::opt(self.aging_target),
+            snapshot::name(self.discipline),
+            self.rng.state()
+        )
+    }
+
+    // An empty queue, processes are restored in order with restore_process
+    pub fn from_snapshot(fields: &mut Fields) -> Option<Queue> {
+        let mut queue = Queue::new(fields.u32()?, fields.u32()?, fields.bool()?);
+        queue.set_aging(fields.u32()?, fields.opt_usize()?);
+        queue.set_discipline(fields.value()?);
+        queue.rng = Rng::new(fields.u64()?);
+        fields.end()?;
+        Some(queue)
+    }
+
+    // Append a process as it was, without touching its allotment
+    pub fn restore_process(&mut self, process: Process) {
+        self.processes.push(process);
+    }
+
+    pub fn put_process_back(&mut self, mut process: Process, bump: bool) {
+        // Refill the allotment of a process that stays at this level after using it up,
+        // e.g. in the lowest queue or when staying after I/O
+        if process.allotment() == 0 {
+            process.set_allotment(self.allotment());
+        }
+
         if bump {
             if let Some(idx) = self
                 .processes
@@ -88,6 +264,9 @@ impl Queue {
 
 impl From<QueueConfig> for Queue {
     fn from(config: QueueConfig) -> Self {
-        Queue::new(config.quantum(), config.allotment(), config.push_front())
+        let mut queue = Queue::new(config.quantum(), config.allotment(), config.push_front());
+        queue.set_aging(config.aging_threshold(), config.aging_target());
+        queue.set_discipline(config.discipline());
+        queue
     }
 }
@@ -0,0 +1,224 @@
+// realtime.rs
+// Real-time scheduling class above the MLFQ: SCHED_FIFO and SCHED_RR jobs with a static priority,
+// optionally throttled to a budget per period like Linux's sched_rt_runtime_us.
+// Author: Hank Bao
+
+use crate::config::SchedClass;
+use crate::process::Process;
+use crate::snapshot::{self, Fields};
+
+pub struct RealTime {
+    // In arrival order, the first ready job of the highest priority runs next
+    processes: Vec<Process>,
+    // (runtime, period): real-time jobs may run for runtime ticks in every period
+    throttle: Option<(u32, u32)>,
+    used: u32,
+    period_start: u32,
+    throttled_count: u32,
+}
+
+impl RealTime {
+    // sched_rr_timeslice, 100ms at one tick per millisecond
+    pub const RR_TIMESLICE: u32 = 100;
+
+    pub fn new() -> RealTime {
+        RealTime {
+            processes: Vec::new(),
+            throttle: None,
+            used: 0,
+            period_start: 0,
+            throttled_count: 0,
+        }
+    }
+
+    pub fn set_throttle(&mut self, runtime: u32, period: u32) {
+        assert!(runtime > 0 && runtime <= period);
+        self.throttle = Some((runtime, period));
+    }
+
+    pub fn throttled_count(&self) -> u32 {
+        self.throttled_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processes.is_empty()
+    }
+
+    pub fn set_trace(&mut self, trace: bool) {
+        for process in self.processes.iter_mut() {
+            process.set_trace(trace);
+        }
+    }
+
+    pub fn processes(&self) -> impl Iterator<Item = &Process> {
+        self.processes.iter()
+    }
+
+    pub fn add_process(&mut self, mut process: Process) {
+        assert!(process.class().is_real_time());
+        // Real-time jobs are never demoted. The allotment of an RR job holds what is left of
+        // its time slice instead, which a job keeps across a suspension, and a FIFO job's
+        // never runs out.
+        if process.allotment() == 0 {
+            process.set_allotment(match process.class() {
+                SchedClass::Rr => Self::RR_TIMESLICE,
+                _ => u32::MAX,
+            });
+        }
+        self.processes.push(process);
+    }
+
+    pub fn take_process(&mut self, pid: u32) -> Option<Process> {
+        self.processes
+            .iter()
+            .position(|p| p.pid() == pid)
+            .map(|i| self.processes.remove(i))
+    }
+
+    // Start a new throttling period if the current one is over
+    pub fn refresh(&mut self, now: u32) {
+        if let Some((_, period)) = self.throttle {
+            while now >= self.period_start + period {
+                self.period_start += period;
+                self.used = 0;
+            }
+        }
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        matches!(self.throttle, Some((runtime, _)) if self.used >= runtime)
+    }
+
+    // The earliest time a real-time job may run, None if there is no real-time job
+    pub fn available_at(&self, now: u32) -> Option<u32> {
+        let ready = self
+            .processes
+            .iter()
+            .map(|p| p.next_schedule_time())
+            .min()?;
+        match self.throttle {
+            Some((_, period)) if self.is_throttled() => Some(ready.max(self.period_start + period)),
+            _ => Some(ready.max(now)),
+        }
+    }
+
+    pub fn take_next_schedulable_process(&mut self, now: u32) -> Option<Process> {
+        if self.is_throttled() {
+            return None;
+        }
+
+        let priority = self
+            .processes
+            .iter()
+            .filter(|p| p.next_schedule_time() <= now)
+            .map(|p| p.rt_priority())
+            .max()?;
+        self.processes
+            .iter()
+            .position(|p| p.next_schedule_time() <= now && p.rt_priority() == priority)
+            .map(|i| self.processes.remove(i))
+    }
+
+    // How long the process may run before it is preempted by a higher priority job,
+    // its time slice runs out or the throttling budget is used up
+    pub fn time_slice(&self, process: &Process, now: u32) -> u32 {
+        let mut slice = match process.class() {
+            SchedClass::Rr => process.allotment(),
+            _ => u32::MAX,
+        };
+
+        if let Some(arrival) = self
+            .processes
+            .iter()
+            .filter(|p| p.rt_priority() > process.rt_priority())
+            .map(|p| p.next_schedule_time())
+            .min()
+        {
+            slice = slice.min(arrival.saturating_sub(now).max(1));
+        }
+
+        if let Some((runtime, period)) = self.throttle {
+            slice = slice
+                .min(runtime - self.used)
+                .min(self.period_start + period - now);
+        }
+
+        slice
+    }
+
+    // Charge the run against the budget, true if it used the budget up
+    pub fn charge(&mut self, run_time: u32) -> bool {
+        if self.throttle.is_none() {
+            return false;
+        }
+
+        self.used += run_time;
+        if self.is_throttled() {
+            self.throttled_count += 1;
+            return true;
+        }
+
+        false
+    }
+
+    // A preempted job stays at the head of its priority with what is left of its slice,
+    // otherwise it goes to the tail. An RR job that used up its slice gets a new one.
+    pub fn put_process_back(&mut self, mut process: Process, preempted: bool) {
+        if process.class() == SchedClass::Rr && process.allotment() == 0 {
+            process.set_allotment(Self::RR_TIMESLICE);
+        }
+
+        if preempted {
+            self.processes.insert(0, process);
+        } else {
+            self.processes.push(process);
+        }
+    }
+
+    // The fields of a "realtime" snapshot line, its processes follow on their own lines
+    pub fn snapshot(&self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            snapshot::opt(self.throttle.map(|(runtime, _)| runtime)),
+            snapshot::opt(self.throttle.map(|(_, period)| period)),
+            self.used,
+            self.period_start,
+            self.throttled_count
+        )
+    }
+
+    pub fn from_snapshot(fields: &mut Fields) -> Option<RealTime> {
+        let throttle = match (fields.opt_u32()?, fields.opt_u32()?) {
+            (Some(runtime), Some(period)) if runtime > 0 && runtime <= period => {
+                Some((runtime, period))
+            }
+            (None, None) => None,
+            _ => return None,
+        };
+        let realtime = RealTime {
+            processes: Vec::new(),
+            throttle,
+            used: fields.u32()?,
+            period_start: fields.u32()?,
+            throttled_count: fields.u32()?,
+        };
+        fields.end()?;
+        Some(realtime)
+    }
+
+    // Append a process as it was
+    pub fn restore_process(&mut self, process: Process) {
+        self.processes.push(process);
+    }
+
+    // When the current throttling period ends
+    pub fn period_end(&self) -> Option<u32> {
+        self.throttle.map(|(_, period)| self.period_start + period)
+    }
+}
+
+impl Default for RealTime {
+    fn default() -> Self {
+        RealTime::new()
+    }
+}
@@ -0,0 +1,43 @@
+// rng.rs
+// Small seeded pseudo-random number generator (SplitMix64), so runs are reproducible.
+// Author: Hank Bao
+
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    // The internal state, Rng::new(state) continues the same sequence
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform value in [0, n)
+    pub fn below(&mut self, n: u32) -> u32 {
+        assert!(n > 0);
+        (self.next_u64() % n as u64) as u32
+    }
+
+    // Uniform value in [lo, hi]
+    pub fn range(&mut self, lo: u32, hi: u32) -> u32 {
+        assert!(lo <= hi);
+        lo + (self.next_u64() % (hi as u64 - lo as u64 + 1)) as u32
+    }
+
+    // Uniform value in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -2,58 +2,450 @@
 // Scheduler for the Multi-Level Feedback Queue (MLFQ) scheduling algorithm.
 // Author: Hank Bao
 
-use crate::config::{JobConfig, QueueConfig, SchedulerConfig};
-use crate::process::Process;
+use crate::config::{ChildPlacement, JobConfig, QueueConfig, SchedClass, SchedulerConfig, Spawn};
+use crate::dispatch::{DispatchEntry, DispatchTable};
+use crate::events::{Event, EventKind};
+use crate::policy::Policy;
+use crate::process::{Level, Process};
 use crate::queue::Queue;
+use crate::realtime::RealTime;
+use crate::snapshot::{self, Fields};
+
+// Where the process lines of a snapshot go
+enum SnapshotTarget {
+    None,
+    Queue(usize),
+    RealTime,
+    Idle,
+    Finished,
+    Killed,
+    Suspended(usize),
+}
+
+// A contiguous stretch of CPU time given to one process
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Slice {
+    pid: u32,
+    start: u32,
+    length: u32,
+}
+
+impl Slice {
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+}
 
 pub struct Scheduler {
     queues: Vec<Queue>,
+    realtime: RealTime,
+    // SCHED_IDLE jobs, run round-robin when no other job is schedulable
+    idle: Queue,
+    finished: Vec<Process>,
+    killed: Vec<Process>,
+    // Suspended processes with the queue level they return to
+    suspended: Vec<(Process, usize)>,
+    // Events not applied yet, in time order
+    events: Vec<Event>,
+    // Jobs held outside the queues until their dependencies finish, with their pids
+    waiting: Vec<(u32, JobConfig)>,
+    child_placement: ChildPlacement,
+    // Children not spawned yet, with the pids of their parents
+    spawns: Vec<(u32, Spawn)>,
+    // Pairs of parent and child pids, in the order the children were spawned
+    children: Vec<(u32, u32)>,
+    slices: Vec<Slice>,
+    // The jobs as given, before periodic ones are released, and the task of every released
+    // pid, kept for the deadline and DAG reports
+    task_configs: Vec<JobConfig>,
+    tasks: Vec<usize>,
+    running: Option<u32>,
+    explain: bool,
     current_time: u32,
     last_boost_time: u32,
+    last_update_time: u32,
     config: SchedulerConfig,
+    dispatch_table: Option<DispatchTable>,
+    policy: Option<Box<dyn Policy>>,
     pid_counter: u32,
     idle_counter: u32,
     idle_total: u32,
     turnaround_total: u32,
     response_total: u32,
+    trace: bool,
 }
 
 impl Scheduler {
     pub fn new(config: SchedulerConfig, queue_configs: Vec<QueueConfig>) -> Scheduler {
+        let idle_quantum = queue_configs.last().map_or(10, |q| q.quantum());
         Scheduler {
             queues: queue_configs.into_iter().map(Queue::from).collect(),
+            realtime: RealTime::new(),
+            idle: Queue::new(idle_quantum, u32::MAX, false),
+            finished: Vec::new(),
+            killed: Vec::new(),
+            suspended: Vec::new(),
+            events: Vec::new(),
+            waiting: Vec::new(),
+            child_placement: ChildPlacement::Top,
+            spawns: Vec::new(),
+            children: Vec::new(),
+            slices: Vec::new(),
+            task_configs: Vec::new(),
+            tasks: Vec::new(),
+            running: None,
+            explain: false,
             current_time: 0,
             last_boost_time: 0,
+            last_update_time: 0,
             config,
+            dispatch_table: None,
+            policy: None,
             pid_counter: 0,
             idle_counter: 0,
             idle_total: 0,
             turnaround_total: 0,
             response_total: 0,
+            trace: true,
         }
     }
 
+    // Create a scheduler driven by a Solaris style dispatch table instead of allotments.
+    // Queue i holds table priority levels() - 1 - i, and io_bump and io_stay are ignored.
+    pub fn with_dispatch_table(config: SchedulerConfig, table: DispatchTable) -> Scheduler {
+        let mut scheduler = Scheduler::new(config, table.queue_configs());
+        scheduler.dispatch_table = Some(table);
+        scheduler
+    }
+
+    // Create a scheduler that follows the given policy instead of the MLFQ rules.
+    // The boost, aging, io_bump and io_stay settings do not apply.
+    pub fn with_policy(config: SchedulerConfig, policy: Box<dyn Policy>) -> Scheduler {
+        let mut scheduler = Scheduler::new(config, policy.queue_configs());
+        scheduler.policy = Some(policy);
+        scheduler
+    }
+
+    pub fn policy(&self) -> Option<&dyn Policy> {
+        self.policy.as_deref()
+    }
+
+    pub fn dispatch_table(&self) -> Option<&DispatchTable> {
+        self.dispatch_table.as_ref()
+    }
+
     pub fn add_jobs(&mut self, jobs: Vec<JobConfig>) {
         for job in jobs {
             self.add_job(job);
         }
     }
 
+    // Also applies to the jobs already added, e.g. those restored from a snapshot
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+        for queue in self.queues.iter_mut() {
+            queue.set_trace(trace);
+        }
+        self.realtime.set_trace(trace);
+        self.idle.set_trace(trace);
+    }
+
+    // Seed the draws of the lottery queues, each queue gets its own stream
+    pub fn set_seed(&mut self, seed: u64) {
+        for (i, queue) in self.queues.iter_mut().enumerate() {
+            queue.set_seed(seed.wrapping_add(i as u64));
+        }
+    }
+
+    // Let real-time jobs run for at most runtime ticks in every period
+    pub fn set_rt_throttle(&mut self, runtime: u32, period: u32) {
+        self.realtime.set_throttle(runtime, period);
+    }
+
+    pub fn realtime(&self) -> &RealTime {
+        &self.realtime
+    }
+
+    // Keep the jobs as given and the task of every released pid for the reports,
+    // they are saved in snapshots
+    pub fn set_tasks(&mut self, task_configs: Vec<JobConfig>, tasks: Vec<usize>) {
+        self.task_configs = task_configs;
+        self.tasks = tasks;
+    }
+
+    pub fn task_configs(&self) -> &[JobConfig] {
+        &self.task_configs
+    }
+
+    pub fn tasks(&self) -> &[usize] {
+        &self.tasks
+    }
+
+    // Annotate every scheduling decision with the MLFQ rule that caused it
+    pub fn set_explain(&mut self, explain: bool) {
+        self.explain = explain;
+    }
+
+    // A job with dependencies that have not finished waits outside the queues.
+    // Pids are handed out in the order jobs are added, whether they wait or not.
     pub fn add_job(&mut self, job: JobConfig) {
-        let proc = Process::new(
-            self.pid_counter,
+        let pid = self.pid_counter;
+        self.pid_counter += 1;
+
+        if !self.dependencies_finished(&job) {
+            if self.trace {
+                println!(
+                    "[{}:<S>] Process {} waits for {}.",
+                    self.current_time,
+                    pid,
+                    Self::pid_list(job.dependencies())
+                );
+            }
+            self.waiting.push((pid, job));
+            return;
+        }
+
+        self.admit_job(pid, job, None);
+    }
+
+    // Put a job in its class, or in a queue picked by the policy, the inherited level
+    // of a spawned child or queue 0
+    fn admit_job(&mut self, pid: u32, job: JobConfig, inherited: Option<usize>) {
+        for spawn in job.spawns() {
+            self.spawns.push((pid, *spawn));
+        }
+
+        let mut proc = Process::new(
+            pid,
             job.io_interval(),
             job.io_length(),
             job.workload(),
             job.arrival_time(),
         );
-        self.pid_counter += 1;
+        proc.set_trace(self.trace);
+        proc.set_tickets(job.tickets());
+        proc.set_class(job.class(), job.rt_priority());
+        proc.set_deadline(job.deadline().map(|d| job.arrival_time() + d));
+
+        // The real-time and idle classes are outside the queues of the MLFQ or policy
+        if job.class() != SchedClass::Other {
+            if self.explain {
+                println!(
+                    "[{}:<E>] Placed pid {} arriving at {} in the {} class.",
+                    self.current_time,
+                    proc.pid(),
+                    proc.start_time(),
+                    Self::class_level(&proc)
+                );
+            }
+
+            if job.class().is_real_time() {
+                self.realtime.add_process(proc);
+            } else {
+                self.idle.add_process(proc);
+            }
+            return;
+        }
+
+        if let Some(policy) = self.policy.as_mut() {
+            let queue = policy.admit(&proc, &job, self.current_time);
+            if self.explain {
+                println!(
+                    "[{}:<E>] Placed pid {} arriving at {} in queue {} ({}).",
+                    self.current_time,
+                    proc.pid(),
+                    proc.start_time(),
+                    queue,
+                    policy.name()
+                );
+            }
 
-        self.queues[0].add_process(proc);
+            self.queues[queue].add_process(proc);
+            return;
+        }
+
+        let (queue, reason) = match inherited {
+            Some(level) => (level, "a spawned child inherits the level of its parent"),
+            None => (0, "Rule 3: new jobs enter at the highest priority"),
+        };
+        if self.explain {
+            println!(
+                "[{}:<E>] Placed pid {} arriving at {} in queue {} ({}).",
+                self.current_time,
+                proc.pid(),
+                proc.start_time(),
+                queue,
+                reason
+            );
+        }
+
+        self.queues[queue].add_process(proc);
     }
 
+    // Suspended jobs keep the run going while an event may still resume them
     pub fn is_finished(&self) -> bool {
         self.queues.iter().all(|q| q.is_empty())
+            && self.realtime.is_empty()
+            && self.idle.is_empty()
+            && (self.suspended.is_empty() || self.events.is_empty())
+    }
+
+    pub fn run_to_completion(&mut self) {
+        while !self.is_finished() {
+            self.run_tick();
+        }
+    }
+
+    pub fn current_time(&self) -> u32 {
+        self.current_time
+    }
+
+    pub fn config(&self) -> SchedulerConfig {
+        self.config
+    }
+
+    // Change the boost and I/O settings mid-run
+    pub fn set_config(&mut self, config: SchedulerConfig) {
+        self.config = config;
+    }
+
+    // Change the settings of a queue mid-run, keeping its processes
+    pub fn reconfigure_queue(&mut self, level: usize, config: QueueConfig) {
+        self.queues[level].reconfigure(config);
+    }
+
+    // The queues from the highest priority to the lowest
+    pub fn queues(&self) -> &[Queue] {
+        &self.queues
+    }
+
+    pub fn finished_processes(&self) -> &[Process] {
+        &self.finished
+    }
+
+    pub fn killed_processes(&self) -> &[Process] {
+        &self.killed
+    }
+
+    pub fn suspended_processes(&self) -> impl Iterator<Item = &Process> {
+        self.suspended.iter().map(|(p, _)| p)
+    }
+
+    // Jobs whose dependencies have not finished, with their pids
+    pub fn waiting_jobs(&self) -> &[(u32, JobConfig)] {
+        &self.waiting
+    }
+
+    fn dependencies_finished(&self, job: &JobConfig) -> bool {
+        job.dependencies()
+            .iter()
+            .all(|&d| self.finished.iter().any(|p| p.pid() == d))
+    }
+
+    // Admit the waiting jobs whose dependencies have all finished, arriving now at the earliest
+    fn release_waiting(&mut self) {
+        let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.waiting)
+            .into_iter()
+            .partition(|(_, job)| self.dependencies_finished(job));
+        self.waiting = waiting;
+
+        for (pid, mut job) in ready {
+            job.set_arrival_time(job.arrival_time().max(self.current_time));
+            if self.trace {
+                println!(
+                    "[{}:<S>] Process {} released, {} finished.",
+                    self.current_time,
+                    pid,
+                    Self::pid_list(job.dependencies())
+                );
+            }
+            self.admit_job(pid, job, None);
+        }
+    }
+
+    // The waiting jobs that depend on the given job, directly or through other waiting jobs
+    fn waiting_dependents(&self, pid: u32) -> Vec<u32> {
+        let mut dependents = vec![pid];
+        let mut next = 0;
+        while next < dependents.len() {
+            let done = dependents[next];
+            next += 1;
+            for (waiting, job) in self.waiting.iter() {
+                if job.dependencies().contains(&done) && !dependents.contains(waiting) {
+                    dependents.push(*waiting);
+                }
+            }
+        }
+        dependents.remove(0);
+        dependents.sort_unstable();
+        dependents
+    }
+
+    fn pid_list(pids: &[u32]) -> String {
+        let list = pids.iter().map(|p| p.to_string()).collect::<Vec<String>>();
+        match pids.len() {
+            1 => format!("process {}", list[0]),
+            _ => format!("processes {}", list.join(", ")),
+        }
+    }
+
+    // Whether spawned children start at the top or at the level of their parent.
+    // Policies and the real-time and idle classes place children like any new job.
+    pub fn set_child_placement(&mut self, placement: ChildPlacement) {
+        self.child_placement = placement;
+    }
+
+    // Pairs of parent and child pids, in the order the children were spawned
+    pub fn children(&self) -> &[(u32, u32)] {
+        &self.children
+    }
+
+    // Add events to apply during the run, each at the first scheduling decision at its time
+    pub fn add_events(&mut self, events: Vec<Event>) {
+        self.events.extend(events);
+        self.events.sort_by_key(|e| e.time());
+    }
+
+    // The events not applied yet
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    // The process that ran during the last tick, None if the CPU was idle
+    pub fn running_process(&self) -> Option<&Process> {
+        let pid = self.running?;
+        self.queues
+            .iter()
+            .flat_map(|q| q.processes())
+            .chain(self.realtime.processes())
+            .chain(self.idle.processes())
+            .chain(self.suspended_processes())
+            .chain(self.finished.iter())
+            .chain(self.killed.iter())
+            .find(|p| p.pid() == pid)
+    }
+
+    // Time left until the next priority boost, None if boosting is disabled
+    pub fn time_to_next_boost(&self) -> Option<u32> {
+        let interval = self.config.priority_boost_interval();
+        if interval == 0 {
+            return None;
+        }
+
+        Some((self.last_boost_time + interval).saturating_sub(self.current_time))
+    }
+
+    // Every stretch of CPU time handed out so far, in time order
+    pub fn slices(&self) -> &[Slice] {
+        &self.slices
     }
 
     pub fn total_idle_time(&self) -> u32 {
@@ -61,11 +453,292 @@ impl Scheduler {
     }
 
     pub fn average_turnaround_time(&self) -> u32 {
-        self.turnaround_total / self.pid_counter
+        self.turnaround_total / self.counted_jobs()
     }
 
     pub fn average_response_time(&self) -> u32 {
-        self.response_total / self.pid_counter
+        self.response_total / self.counted_jobs()
+    }
+
+    // Killed jobs, jobs left suspended and jobs still waiting never finish,
+    // so they are not in the averages
+    fn counted_jobs(&self) -> u32 {
+        let uncounted = (self.killed.len() + self.suspended.len() + self.waiting.len()) as u32;
+        (self.pid_counter - uncounted).max(1)
+    }
+
+    // The whole state of the scheduler in the snapshot format. None with a policy,
+    // whose state is private to it.
+    pub fn snapshot(&self) -> Option<String> {
+        if let Some(policy) = self.policy.as_ref() {
+            eprintln!(
+                "A snapshot cannot be taken with the {} policy.",
+                policy.name()
+            );
+            return None;
+        }
+
+        let mut lines = vec![
+            snapshot::HEADER.to_string(),
+            format!("version {}", snapshot::VERSION),
+            format!(
+                "config {} {} {}",
+                self.config.priority_boost_interval(),
+                self.config.io_bump(),
+                self.config.io_stay()
+            ),
+            format!(
+                "clock {} {} {}",
+                self.current_time, self.last_boost_time, self.last_update_time
+            ),
+            format!(
+                "counters {} {} {} {} {}",
+                self.pid_counter,
+                self.idle_counter,
+                self.idle_total,
+                self.turnaround_total,
+                self.response_total
+            ),
+            format!("running {}", snapshot::opt(self.running)),
+            format!("output {} {}", self.trace, self.explain),
+        ];
+
+        if let Some(table) = self.dispatch_table.as_ref() {
+            lines.push(format!("table {}", table.update_interval()));
+            for priority in 0..table.levels() {
+                let e = table.entry(priority);
+                lines.push(format!(
+                    "entry {} {} {} {} {}",
+                    e.quantum(),
+                    e.tqexp(),
+                    e.slpret(),
+                    e.maxwait(),
+                    e.lwait()
+                ));
+            }
+        }
+
+        let processes = |lines: &mut Vec<String>, processes: &mut dyn Iterator<Item = &Process>| {
+            lines.extend(processes.map(|p| format!("process {}", p.snapshot())));
+        };
+        for queue in self.queues.iter() {
+            lines.push(format!("queue {}", queue.snapshot()));
+            processes(&mut lines, &mut queue.processes());
+        }
+        lines.push(format!("realtime {}", self.realtime.snapshot()));
+        processes(&mut lines, &mut self.realtime.processes());
+        lines.push(format!("idle {}", self.idle.snapshot()));
+        processes(&mut lines, &mut self.idle.processes());
+        lines.push("finished".to_string());
+        processes(&mut lines, &mut self.finished.iter());
+        lines.push("killed".to_string());
+        processes(&mut lines, &mut self.killed.iter());
+        for (process, level) in self.suspended.iter() {
+            lines.push(format!("suspended {}", level));
+            lines.push(format!("process {}", process.snapshot()));
+        }
+        for (pid, job) in self.waiting.iter() {
+            lines.push(format!("waiting {} {}", pid, job.snapshot()));
+        }
+        lines.push(format!(
+            "placement {}",
+            snapshot::name(self.child_placement)
+        ));
+        for (pid, spawn) in self.spawns.iter() {
+            lines.push(format!("spawn {} {}", pid, spawn.spec()));
+        }
+        for (parent, child) in self.children.iter() {
+            lines.push(format!("child {} {}", parent, child));
+        }
+        for event in self.events.iter() {
+            lines.push(format!("event {}", event.spec()));
+        }
+        for slice in self.slices.iter() {
+            lines.push(format!(
+                "slice {} {} {}",
+                slice.pid, slice.start, slice.length
+            ));
+        }
+        for job in self.task_configs.iter() {
+            lines.push(format!("task {} {}", job.period(), job.snapshot()));
+        }
+        if !self.tasks.is_empty() {
+            let tasks = self
+                .tasks
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<String>>();
+            lines.push(format!("tasks {}", tasks.join(",")));
+        }
+
+        lines.push(String::new());
+        Some(lines.join("\n"))
+    }
+
+    // An identical copy that continues independently, None with a policy
+    pub fn fork(&self) -> Option<Scheduler> {
+        Scheduler::from_snapshot(&self.snapshot()?)
+    }
+
+    // Restore a scheduler from a snapshot, so it continues exactly where the snapshot was taken
+    pub fn from_snapshot(text: &str) -> Option<Scheduler> {
+        let mut scheduler = Scheduler::new(SchedulerConfig::new(0, false, false), Vec::new());
+        let mut target = SnapshotTarget::None;
+        let mut table = None;
+        let mut entries = Vec::new();
+        let mut has_version = false;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let mut fields = Fields::new(rest);
+            let valid = match keyword {
+                "version" => {
+                    has_version = true;
+                    fields.u32().filter(|&v| v == snapshot::VERSION).is_some()
+                }
+                "table" => {
+                    table = fields.u32();
+                    table.is_some()
+                }
+                "entry" => {
+                    let entry = (|| {
+                        let entry = DispatchEntry::new(
+                            fields.u32()?,
+                            fields.usize()?,
+                            fields.usize()?,
+                            fields.u32()?,
+                            fields.usize()?,
+                        );
+                        fields.end()?;
+                        Some(entry)
+                    })();
+                    entry.map(|e| entries.push(e)).is_some()
+                }
+                _ => scheduler
+                    .read_snapshot_line(keyword, &mut fields, &mut target)
+                    .is_some(),
+            };
+
+            if !valid {
+                eprintln!("Invalid snapshot line {}: {}", number + 1, line);
+                return None;
+            }
+        }
+
+        if !has_version || scheduler.queues.is_empty() {
+            eprintln!("The snapshot has no version or no queue.");
+            return None;
+        }
+
+        if let Some(interval) = table {
+            scheduler.dispatch_table = DispatchTable::new(entries, interval)
+                .filter(|t| t.levels() == scheduler.queues.len());
+            if scheduler.dispatch_table.is_none() {
+                eprintln!("The dispatch table of the snapshot does not match its queues.");
+                return None;
+            }
+        }
+
+        Some(scheduler)
+    }
+
+    fn read_snapshot_line(
+        &mut self,
+        keyword: &str,
+        fields: &mut Fields,
+        target: &mut SnapshotTarget,
+    ) -> Option<()> {
+        match keyword {
+            "config" => {
+                self.config = SchedulerConfig::new(fields.u32()?, fields.bool()?, fields.bool()?);
+            }
+            "clock" => {
+                self.current_time = fields.u32()?;
+                self.last_boost_time = fields.u32()?;
+                self.last_update_time = fields.u32()?;
+            }
+            "counters" => {
+                self.pid_counter = fields.u32()?;
+                self.idle_counter = fields.u32()?;
+                self.idle_total = fields.u32()?;
+                self.turnaround_total = fields.u32()?;
+                self.response_total = fields.u32()?;
+            }
+            "running" => self.running = fields.opt_u32()?,
+            "output" => {
+                self.trace = fields.bool()?;
+                self.explain = fields.bool()?;
+            }
+            "queue" => {
+                self.queues.push(Queue::from_snapshot(fields)?);
+                *target = SnapshotTarget::Queue(self.queues.len() - 1);
+                return Some(());
+            }
+            "realtime" => {
+                self.realtime = RealTime::from_snapshot(fields)?;
+                *target = SnapshotTarget::RealTime;
+                return Some(());
+            }
+            "idle" => {
+                self.idle = Queue::from_snapshot(fields)?;
+                *target = SnapshotTarget::Idle;
+                return Some(());
+            }
+            "finished" => *target = SnapshotTarget::Finished,
+            "killed" => *target = SnapshotTarget::Killed,
+            "suspended" => *target = SnapshotTarget::Suspended(fields.usize()?),
+            "process" => {
+                let process = Process::from_snapshot(fields)?;
+                match target {
+                    SnapshotTarget::Queue(index) => self.queues[*index].restore_process(process),
+                    SnapshotTarget::RealTime => self.realtime.restore_process(process),
+                    SnapshotTarget::Idle => self.idle.restore_process(process),
+                    SnapshotTarget::Finished => self.finished.push(process),
+                    SnapshotTarget::Killed => self.killed.push(process),
+                    SnapshotTarget::Suspended(level) => self.suspended.push((process, *level)),
+                    SnapshotTarget::None => return None,
+                }
+                return Some(());
+            }
+            "waiting" => {
+                let pid = fields.u32()?;
+                self.waiting.push((pid, JobConfig::from_snapshot(fields)?));
+                return Some(());
+            }
+            "task" => {
+                let period = fields.u32()?;
+                let mut job = JobConfig::from_snapshot(fields)?;
+                job.set_period(period);
+                self.task_configs.push(job);
+                return Some(());
+            }
+            "tasks" => {
+                self.tasks = fields
+                    .word()?
+                    .split(',')
+                    .map(|t| t.parse().ok())
+                    .collect::<Option<Vec<usize>>>()?;
+            }
+            "placement" => self.child_placement = fields.value()?,
+            "spawn" => self
+                .spawns
+                .push((fields.u32()?, Spawn::parse(fields.word()?)?)),
+            "child" => self.children.push((fields.u32()?, fields.u32()?)),
+            "event" => self.events.push(Event::parse(fields.word()?)?),
+            "slice" => self.slices.push(Slice {
+                pid: fields.u32()?,
+                start: fields.u32()?,
+                length: fields.u32()?,
+            }),
+            _ => return None,
+        }
+
+        fields.end()
     }
 
     // Based on the MLFQ rules described in "Operating Systems: Three Easy Pieces"
@@ -75,41 +748,57 @@ impl Scheduler {
     // 3. When a job enters the system, it is placed at the highest priority (the topmost queue).
     // 4. Once a job uses up its time allotment at a given level, its priority is reduced
     // 5. After some time period S, move all the jobs in the system to the topmost queue.
+    //
+    // Real-time jobs run before any of these rules apply, and idle jobs only when none applies.
+    // Events that are due are applied first.
     pub fn run_tick(&mut self) {
+        self.apply_events();
+        // Nothing is left to run once the events killed or suspended the last jobs
+        if self.is_finished() {
+            return;
+        }
+
+        if self.run_real_time() {
+            return;
+        }
+
+        if self.policy.is_some() {
+            self.run_policy_tick();
+            return;
+        }
+
         // Check if we need to do a priority boost
         if self.priority_boost_check() {
             self.do_priority_boost();
         }
 
+        // Promote the jobs that waited longer than the aging threshold of their queue
+        self.do_aging();
+
+        // Age the jobs that waited too long, when driven by a dispatch table
+        if self.dispatch_update_check() {
+            self.do_dispatch_update();
+        }
+
         // Find the next schedulable process
         if let Some(index) = self.find_runnable_queue() {
             let process = self.queues[index].take_next_schedulable_process(self.current_time);
 
             if let Some(mut process) = process {
-                if self.idle_counter > 0 {
-                    println!(
-                        "[{}:<S>] CPU idle for {} ticks.",
-                        self.current_time, self.idle_counter
-                    );
-                    self.idle_counter = 0;
+                if self.explain {
+                    self.explain_pick(index, &process);
                 }
 
-                let quantum = self.queues[index].quantum();
-                let run_time = process.run(quantum, self.current_time, index);
-                self.current_time += run_time;
+                let quantum = self.queues[index]
+                    .time_slice()
+                    .min(self.time_to_rt())
+                    .min(self.time_to_event());
+                self.run_process(&mut process, quantum, Level::Queue(index));
 
                 if process.is_finished() {
-                    // Process finished, print its response time & turnaround time
-                    println!(
-                        "[{}:<S>] Process {} finished. Response time: {}. Turnaround time: {}.",
-                        self.current_time,
-                        process.pid(),
-                        process.response_time(),
-                        process.turnaround_time()
-                    );
-
-                    self.turnaround_total += process.turnaround_time();
-                    self.response_total += process.response_time();
+                    self.finish_process(process);
+                } else if self.dispatch_table.is_some() {
+                    self.dispatch_by_table(process, index);
                 } else {
                     // Rule 4, reduce the priority of the process
                     let pid = process.pid();
@@ -119,39 +808,485 @@ impl Scheduler {
                         // reset the next schedule time for the process
                         self.queues[index + 1].add_process(process);
 
-                        println!(
-                            "[{}:<S>] Process {} priority reduced to {}.",
-                            self.current_time,
-                            pid,
-                            index + 1
-                        );
+                        if self.trace {
+                            println!(
+                                "[{}:<S>] Process {} priority reduced to {}.",
+                                self.current_time,
+                                pid,
+                                index + 1
+                            );
+                        }
+                        if self.explain {
+                            println!(
+                                "[{}:<E>] Demoted pid {} from queue {} to queue {} (Rule 4: allotment exhausted).",
+                                self.current_time,
+                                pid,
+                                index,
+                                index + 1
+                            );
+                        }
                     } else {
-                        if do_io_stay {
+                        if self.explain {
+                            self.explain_put_back(&process, index, do_io_stay);
+                        }
+
+                        if do_io_stay && self.trace {
                             println!(
                                 "[{}:<{}>] Process {} stay after I/O.",
-                                self.current_time, index, pid,
+                                self.current_time,
+                                Level::Queue(index),
+                                pid,
                             );
                         }
 
                         let do_io_bump = self.config.io_bump() && process.is_blocked();
                         self.queues[index].put_process_back(process, do_io_bump);
 
-                        if do_io_bump {
+                        if do_io_bump && self.trace {
                             println!(
                                 "[{}:<{}>] Process {} bumped after I/O.",
-                                self.current_time, index, pid,
+                                self.current_time,
+                                Level::Queue(index),
+                                pid,
                             );
                         }
                     }
                 }
             }
         } else {
-            self.idle_counter += 1;
-            self.idle_total += 1;
-            self.current_time += 1;
+            self.run_idle_class();
         }
     }
 
+    // One scheduling round under a policy. The policy is taken out of the scheduler
+    // for the duration of the round and handed back at the end.
+    // The policy stays in the scheduler for the whole tick, so it admits the children a
+    // process spawns and the jobs released when their dependencies finish
+    fn run_policy_tick(&mut self) {
+        let now = self.current_time;
+        let policy = self.policy.as_mut().expect("policy tick without a policy");
+        let name = policy.name();
+        for (pid, target) in policy.update(&self.queues, now) {
+            self.move_process(pid, target, name);
+        }
+
+        let policy = self.policy.as_mut().expect("policy tick without a policy");
+        let (index, pid) = match policy.pick(&self.queues, now) {
+            Some(choice) => choice,
+            None => {
+                self.run_idle_class();
+                return;
+            }
+        };
+
+        if self.explain {
+            println!(
+                "[{}:<E>] Picked pid {} from queue {} ({}).",
+                self.current_time, pid, index, name
+            );
+        }
+
+        let mut process = self.queues[index]
+            .take_process(pid)
+            .expect("policy picked a process that is not in the queue");
+        let quantum = self
+            .policy
+            .as_ref()
+            .expect("policy tick without a policy")
+            .quantum(&process, index)
+            .min(self.time_to_rt())
+            .min(self.time_to_event());
+        let run_time = self.run_process(&mut process, quantum, Level::Queue(index));
+
+        let now = self.current_time;
+        let policy = self.policy.as_mut().expect("policy tick without a policy");
+        if process.is_finished() {
+            policy.finished(&process, now);
+            self.finish_process(process);
+        } else {
+            let target = policy.ran(&process, index, run_time, now);
+            if target == index {
+                self.queues[index].put_process_back(process, false);
+            } else {
+                self.queues[target].add_process(process);
+                if self.trace {
+                    println!(
+                        "[{}:<S>] Process {} moved from queue {} to {} by {}.",
+                        self.current_time, pid, index, target, name
+                    );
+                }
+            }
+        }
+    }
+
+    fn move_process(&mut self, pid: u32, target: usize, reason: &str) {
+        let index = match self
+            .queues
+            .iter()
+            .position(|q| q.processes().any(|p| p.pid() == pid))
+        {
+            Some(index) => index,
+            None => return,
+        };
+
+        if index == target {
+            return;
+        }
+
+        if let Some(process) = self.queues[index].take_process(pid) {
+            self.queues[target].add_process(process);
+            if self.trace {
+                println!(
+                    "[{}:<S>] Process {} moved from queue {} to {} by {}.",
+                    self.current_time, pid, index, target, reason
+                );
+            }
+        }
+    }
+
+    // Run the highest priority real-time job if one is schedulable and not throttled
+    fn run_real_time(&mut self) -> bool {
+        let now = self.current_time;
+        self.realtime.refresh(now);
+
+        let mut process = match self.realtime.take_next_schedulable_process(now) {
+            Some(process) => process,
+            None => return false,
+        };
+
+        if self.explain {
+            println!(
+                "[{}:<E>] Picked pid {} ({} preempts the time-sharing queues).",
+                now,
+                process.pid(),
+                Self::class_level(&process)
+            );
+        }
+
+        let slice = self
+            .realtime
+            .time_slice(&process, now)
+            .min(self.time_to_event());
+        let level = Self::class_level(&process);
+        let run_time = self.run_process(&mut process, slice, level);
+
+        if self.realtime.charge(run_time) && self.trace {
+            println!(
+                "[{}:<S>] Real-time jobs throttled until {}.",
+                self.current_time,
+                self.realtime.period_end().unwrap_or(0)
+            );
+        }
+
+        if process.is_finished() {
+            self.finish_process(process);
+        } else {
+            // Cut short by a higher priority job or the throttle rather than by its own slice
+            let full = process.class() == SchedClass::Rr && process.allotment() == 0;
+            let preempted = !process.is_blocked() && !full;
+            self.realtime.put_process_back(process, preempted);
+        }
+
+        true
+    }
+
+    // Run a SCHED_IDLE job, or let the CPU idle if there is none
+    fn run_idle_class(&mut self) {
+        let now = self.current_time;
+        if !self.idle.has_schedulable_process(now) {
+            self.idle_tick();
+            return;
+        }
+
+        // Any other job becoming schedulable preempts the idle class
+        let next_ready = self
+            .queues
+            .iter()
+            .flat_map(|q| q.processes())
+            .map(|p| p.next_schedule_time())
+            .min()
+            .map_or(u32::MAX, |t| t.saturating_sub(now).max(1));
+        let quantum = self
+            .idle
+            .time_slice()
+            .min(next_ready)
+            .min(self.time_to_rt())
+            .min(self.time_to_event());
+
+        let mut process = self
+            .idle
+            .take_next_schedulable_process(now)
+            .expect("idle class has a schedulable process");
+        if self.explain {
+            println!(
+                "[{}:<E>] Picked pid {} (SCHED_IDLE, nothing else is schedulable).",
+                now,
+                process.pid()
+            );
+        }
+
+        self.run_process(&mut process, quantum, Level::Idle);
+        if process.is_finished() {
+            self.finish_process(process);
+        } else {
+            self.idle.put_process_back(process, false);
+        }
+    }
+
+    // Ticks until a real-time job may preempt whatever runs now
+    fn time_to_rt(&self) -> u32 {
+        match self.realtime.available_at(self.current_time) {
+            Some(at) => at.saturating_sub(self.current_time).max(1),
+            None => u32::MAX,
+        }
+    }
+
+    // Ticks until the next event, so a running job is cut short when it is due
+    fn time_to_event(&self) -> u32 {
+        self.events.first().map_or(u32::MAX, |e| {
+            e.time().saturating_sub(self.current_time).max(1)
+        })
+    }
+
+    fn apply_events(&mut self) {
+        while self
+            .events
+            .first()
+            .is_some_and(|e| e.time() <= self.current_time)
+        {
+            let event = self.events.remove(0);
+            if self.trace {
+                println!(
+                    "[{}:<S>] Event: {}.",
+                    self.current_time,
+                    Self::describe_event(event.kind())
+                );
+            }
+            let applied = self.apply_event(event.kind());
+            if !applied && self.trace {
+                println!(
+                    "[{}:<S>] Event ignored, no such job or queue.",
+                    self.current_time
+                );
+            }
+
+            // The jobs that wait for a killed job stay outside the queues for good
+            if let (true, true, EventKind::Kill(pid)) = (applied, self.trace, event.kind()) {
+                let dependents = self.waiting_dependents(pid);
+                if !dependents.is_empty() {
+                    println!(
+                        "[{}:<S>] Process {} was killed, so {} can never be released.",
+                        self.current_time,
+                        pid,
+                        Self::pid_list(&dependents)
+                    );
+                }
+            }
+        }
+    }
+
+    // False if the event does not apply, e.g. to a job that has finished or does not exist
+    fn apply_event(&mut self, kind: EventKind) -> bool {
+        let now = self.current_time;
+        match kind {
+            EventKind::Kill(pid) => match self.take_live_process(pid) {
+                Some((process, _)) => self.killed.push(process),
+                None => match self.suspended.iter().position(|(p, _)| p.pid() == pid) {
+                    Some(index) => {
+                        let (process, _) = self.suspended.remove(index);
+                        self.killed.push(process);
+                    }
+                    None => return false,
+                },
+            },
+            EventKind::Suspend(pid) => match self.take_live_process(pid) {
+                Some(suspended) => self.suspended.push(suspended),
+                None => return false,
+            },
+            EventKind::Resume(pid) => {
+                let index = match self.suspended.iter().position(|(p, _)| p.pid() == pid) {
+                    Some(index) => index,
+                    None => return false,
+                };
+                let (mut process, level) = self.suspended.remove(index);
+                process.delay_until(now);
+                match process.class() {
+                    class if class.is_real_time() => self.realtime.add_process(process),
+                    SchedClass::Idle => self.idle.add_process(process),
+                    // Back to the tail of its queue with what is left of its allotment
+                    _ => self.queues[level].restore_process(process),
+                }
+            }
+            EventKind::Move(pid, target) => {
+                if target >= self.queues.len() {
+                    return false;
+                }
+                let allotment = self.queues[target].allotment();
+                if let Some((process, level)) = self
+                    .suspended
+                    .iter_mut()
+                    .find(|(p, _)| p.pid() == pid && p.class() == SchedClass::Other)
+                {
+                    process.set_allotment(allotment);
+                    *level = target;
+                    return true;
+                }
+
+                let index = match self
+                    .queues
+                    .iter()
+                    .position(|q| q.processes().any(|p| p.pid() == pid))
+                {
+                    Some(index) => index,
+                    None => return false,
+                };
+                if let Some(process) = self.queues[index].take_process(pid) {
+                    self.queues[target].add_process(process);
+                }
+            }
+            EventKind::Quantum(level, quantum) => match self.queues.get_mut(level) {
+                Some(queue) => queue.set_quantum(quantum),
+                None => return false,
+            },
+            EventKind::BoostInterval(interval) => {
+                // Enabling boosting starts counting the interval now
+                if self.config.priority_boost_interval() == 0 {
+                    self.last_boost_time = now;
+                }
+                self.config =
+                    SchedulerConfig::new(interval, self.config.io_bump(), self.config.io_stay());
+            }
+            EventKind::Boost => self.do_priority_boost(),
+        }
+
+        true
+    }
+
+    // Take a job that has not finished out of its queue or class, with its queue level
+    fn take_live_process(&mut self, pid: u32) -> Option<(Process, usize)> {
+        for (level, queue) in self.queues.iter_mut().enumerate() {
+            if let Some(process) = queue.take_process(pid) {
+                return Some((process, level));
+            }
+        }
+
+        self.realtime
+            .take_process(pid)
+            .or_else(|| self.idle.take_process(pid))
+            .map(|p| (p, 0))
+    }
+
+    fn describe_event(kind: EventKind) -> String {
+        match kind {
+            EventKind::Kill(pid) => format!("kill process {}", pid),
+            EventKind::Suspend(pid) => format!("suspend process {}", pid),
+            EventKind::Resume(pid) => format!("resume process {}", pid),
+            EventKind::Move(pid, level) => format!("move process {} to queue {}", pid, level),
+            EventKind::Quantum(level, quantum) => {
+                format!("set quantum of queue {} to {}", level, quantum)
+            }
+            EventKind::BoostInterval(interval) => format!("set boost interval to {}", interval),
+            EventKind::Boost => "force a priority boost".to_string(),
+        }
+    }
+
+    // The level of a real-time or idle job, which runs outside the time-sharing queues
+    fn class_level(process: &Process) -> Level {
+        match process.class() {
+            SchedClass::Fifo => Level::Fifo(process.rt_priority()),
+            SchedClass::Rr => Level::Rr(process.rt_priority()),
+            SchedClass::Idle | SchedClass::Other => Level::Idle,
+        }
+    }
+
+    // Run the process for up to the quantum and record the CPU time it got
+    fn run_process(&mut self, process: &mut Process, quantum: u32, level: Level) -> u32 {
+        if self.idle_counter > 0 {
+            if self.trace {
+                println!(
+                    "[{}:<S>] CPU idle for {} ticks.",
+                    self.current_time, self.idle_counter
+                );
+            }
+            self.idle_counter = 0;
+        }
+
+        let run_time = process.run(quantum, self.current_time, level);
+        self.running = Some(process.pid());
+        self.slices.push(Slice {
+            pid: process.pid(),
+            start: self.current_time,
+            length: run_time,
+        });
+        self.current_time += run_time;
+        self.spawn_children(process, run_time, level);
+
+        run_time
+    }
+
+    // Spawn the children whose spawn point the process passed in the slice that just ended.
+    // A child arrives at the time its parent reached the point, and inherits its class.
+    fn spawn_children(&mut self, parent: &Process, run_time: u32, level: Level) {
+        let start = self.current_time - run_time;
+        let done_before = parent.work_done() - run_time;
+        let level = match level {
+            Level::Queue(index) if self.child_placement == ChildPlacement::Inherit => Some(index),
+            _ => None,
+        };
+
+        while let Some(index) = self
+            .spawns
+            .iter()
+            .position(|(pid, s)| *pid == parent.pid() && s.at() <= parent.work_done())
+        {
+            let (_, spawn) = self.spawns.remove(index);
+            let at = start + spawn.at().saturating_sub(done_before);
+            let pid = self.pid_counter;
+            self.pid_counter += 1;
+            self.children.push((parent.pid(), pid));
+
+            let mut job =
+                JobConfig::new(at, spawn.workload(), spawn.io_interval(), spawn.io_length());
+            job.set_class(parent.class(), parent.rt_priority());
+            job.set_tickets(parent.tickets());
+            if self.trace {
+                println!(
+                    "[{}:<S>] Process {} spawned process {} at {}.",
+                    self.current_time,
+                    parent.pid(),
+                    pid,
+                    at
+                );
+            }
+            self.admit_job(pid, job, level);
+        }
+    }
+
+    fn finish_process(&mut self, process: Process) {
+        // Process finished, print its response time & turnaround time
+        if self.trace {
+            println!(
+                "[{}:<S>] Process {} finished. Response time: {}. Turnaround time: {}.",
+                self.current_time,
+                process.pid(),
+                process.response_time(),
+                process.turnaround_time()
+            );
+        }
+
+        self.turnaround_total += process.turnaround_time();
+        self.response_total += process.response_time();
+        self.finished.push(process);
+        self.release_waiting();
+    }
+
+    fn idle_tick(&mut self) {
+        self.running = None;
+        self.idle_counter += 1;
+        self.idle_total += 1;
+        self.current_time += 1;
+    }
+
     fn priority_boost_check(&self) -> bool {
         let interval = self.config.priority_boost_interval();
         if interval == 0 {
@@ -169,11 +1304,236 @@ impl Scheduler {
             }
         }
 
+        if self.explain {
+            println!(
+                "[{}:<E>] Boosted all jobs to queue 0 (Rule 5: {} ticks since last boost).",
+                self.current_time,
+                self.current_time - self.last_boost_time
+            );
+        }
+
         self.last_boost_time = self.current_time;
 
+        if self.trace {
+            println!(
+                "[{}:<S>] Priority boosted for all processes.",
+                self.current_time
+            );
+        }
+    }
+
+    fn do_aging(&mut self) {
+        let now = self.current_time;
+
+        for index in 1..self.queues.len() {
+            let threshold = self.queues[index].aging_threshold();
+            if threshold == 0 {
+                continue;
+            }
+
+            let target = self.queues[index].aging_target().unwrap_or(index - 1);
+            let aged = self.queues[index].take_processes(|p| p.waited(now) >= threshold);
+
+            for mut p in aged {
+                if self.trace {
+                    println!(
+                        "[{}:<S>] Process {} waited {} ticks, aged from {} to {}.",
+                        now,
+                        p.pid(),
+                        p.waited(now),
+                        index,
+                        target
+                    );
+                }
+                if self.explain {
+                    println!(
+                        "[{}:<E>] Promoted pid {} from queue {} to queue {} (Aging: waited {} ticks, threshold {}).",
+                        now,
+                        p.pid(),
+                        index,
+                        target,
+                        p.waited(now),
+                        threshold
+                    );
+                }
+
+                p.reset_wait(now);
+                p.add_promotion();
+                self.queues[target].add_process(p);
+            }
+        }
+    }
+
+    // Move a process that used up its time slice or went to sleep, as the dispatch table says
+    fn dispatch_by_table(&mut self, process: Process, index: usize) {
+        let table = match self.dispatch_table.as_ref() {
+            Some(table) => table,
+            None => return,
+        };
+        let priority = table.priority_of(index);
+        let entry = *table.entry(priority);
+
+        // The allotment equals the quantum, so a job that did not block used up its time slice
+        let (target, reason) = if process.is_blocked() {
+            (entry.slpret(), "ts_slpret: returning from sleep")
+        } else {
+            (entry.tqexp(), "ts_tqexp: time slice expired")
+        };
+        let target_queue = table.queue_of(target);
+        let pid = process.pid();
+
+        if target_queue == index {
+            self.queues[index].put_process_back(process, false);
+        } else {
+            self.queues[target_queue].add_process(process);
+
+            if self.trace {
+                println!(
+                    "[{}:<S>] Process {} priority changed from level {} to {}.",
+                    self.current_time, pid, priority, target
+                );
+            }
+        }
+
+        if self.explain {
+            println!(
+                "[{}:<E>] Moved pid {} from level {} to level {} ({}).",
+                self.current_time, pid, priority, target, reason
+            );
+        }
+    }
+
+    fn dispatch_update_check(&self) -> bool {
+        match self.dispatch_table.as_ref() {
+            Some(table) => self.current_time - self.last_update_time >= table.update_interval(),
+            None => false,
+        }
+    }
+
+    // Like ts_update, move every job that waited more than ts_maxwait update intervals to ts_lwait
+    fn do_dispatch_update(&mut self) {
+        let table = match self.dispatch_table.as_ref() {
+            Some(table) => table,
+            None => return,
+        };
+        let now = self.current_time;
+        let interval = table.update_interval();
+
+        let mut aged = Vec::new();
+        for (index, queue) in self.queues.iter_mut().enumerate() {
+            let priority = table.priority_of(index);
+            let entry = *table.entry(priority);
+            let waited_too_long = |p: &Process| p.waited(now) / interval > entry.maxwait();
+
+            for p in queue.take_processes(waited_too_long) {
+                aged.push((priority, entry.lwait(), p));
+            }
+        }
+
+        for (priority, target, mut p) in aged {
+            if self.trace {
+                println!(
+                    "[{}:<S>] Process {} waited {} ticks, priority changed from level {} to {}.",
+                    now,
+                    p.pid(),
+                    p.waited(now),
+                    priority,
+                    target
+                );
+            }
+            if self.explain {
+                println!(
+                    "[{}:<E>] Aged pid {} from level {} to level {} (ts_lwait: waited longer than ts_maxwait).",
+                    now,
+                    p.pid(),
+                    priority,
+                    target
+                );
+            }
+
+            p.reset_wait(now);
+            let target_queue = table.queue_of(target);
+            self.queues[target_queue].add_process(p);
+        }
+
+        self.last_update_time = now;
+    }
+
+    // Called with the picked process already taken out of its queue
+    fn explain_pick(&self, index: usize, picked: &Process) {
+        let now = self.current_time;
+        let queue = &self.queues[index];
+        let order = queue.discipline().order();
+
+        let rule_1 = match index {
+            0 => "Rule 1: queue 0 has the highest priority".to_string(),
+            1 => "Rule 1: queue 0 empty or not yet schedulable".to_string(),
+            _ => format!(
+                "Rule 1: queues 0-{} empty or not yet schedulable",
+                index - 1
+            ),
+        };
+        let rule_2 = if queue.has_schedulable_process(now) {
+            format!(
+                "; Rule 2: {} with the other ready jobs of this queue",
+                order
+            )
+        } else {
+            String::new()
+        };
+        println!(
+            "[{}:<E>] Picked pid {} from queue {} ({}{}).",
+            now,
+            picked.pid(),
+            index,
+            rule_1,
+            rule_2
+        );
+
+        for (i, q) in self.queues.iter().enumerate() {
+            for p in q.processes() {
+                let reason = if p.next_schedule_time() > now {
+                    if p.is_blocked() {
+                        format!("blocked on I/O until {}", p.next_schedule_time())
+                    } else {
+                        format!("not arrived until {}", p.next_schedule_time())
+                    }
+                } else if i > index {
+                    format!("queue {} has lower priority (Rule 1)", i)
+                } else {
+                    format!("behind in {} order (Rule 2)", order)
+                };
+                println!(
+                    "[{}:<E>]   passed over pid {} in queue {}: {}.",
+                    now,
+                    p.pid(),
+                    i,
+                    reason
+                );
+            }
+        }
+    }
+
+    fn explain_put_back(&self, process: &Process, index: usize, io_stay: bool) {
+        let reason = if io_stay {
+            "io_stay: issued I/O, so it keeps its level"
+        } else if process.allotment() == 0 {
+            "Rule 4: allotment exhausted but there is no lower queue"
+        } else {
+            "Rule 4: allotment not yet exhausted"
+        };
+        let order = if self.config.io_bump() && process.is_blocked() {
+            "; io_bump: ahead of jobs that become schedulable later"
+        } else {
+            "; Rule 2: back of the queue for round-robin"
+        };
         println!(
-            "[{}:<S>] Priority boosted for all processes.",
-            self.current_time
+            "[{}:<E>] Kept pid {} in queue {} ({}{}).",
+            self.current_time,
+            process.pid(),
+            index,
+            reason,
+            order
         );
     }
 
@@ -186,3 +1546,142 @@ impl Scheduler {
             .map(|(i, _)| i)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A job of the given class that blocks on I/O after every 10 ticks of its 30
+    fn job(class: SchedClass, rt_priority: u32) -> JobConfig {
+        let mut job = JobConfig::new(0, 30, 10, 5);
+        job.set_class(class, rt_priority);
+        job
+    }
+
+    fn scheduler(jobs: Vec<JobConfig>) -> Scheduler {
+        let mut scheduler = Scheduler::new(
+            SchedulerConfig::new(0, false, false),
+            vec![QueueConfig::new(10, 20, false)],
+        );
+        scheduler.set_trace(false);
+        scheduler.add_jobs(jobs);
+        scheduler
+    }
+
+    #[test]
+    fn running_process_outside_the_queues() {
+        let running = |scheduler: &Scheduler| scheduler.running_process().map(|p| p.pid());
+
+        let mut realtime = scheduler(vec![job(SchedClass::Fifo, 5)]);
+        realtime.run_tick();
+        assert_eq!(running(&realtime), Some(0));
+
+        let mut idle = scheduler(vec![job(SchedClass::Idle, 0)]);
+        idle.run_tick();
+        assert_eq!(running(&idle), Some(0));
+
+        // Suspended right after its slice, which ends the run
+        let mut suspended = scheduler(vec![job(SchedClass::Other, 0)]);
+        suspended.add_events(vec![Event::new(10, EventKind::Suspend(0))]);
+        suspended.run_tick();
+        suspended.run_tick();
+        assert!(suspended.is_finished());
+        assert_eq!(running(&suspended), Some(0));
+    }
+
+    #[test]
+    fn every_policy_admits_released_jobs() {
+        use crate::policy::{PolicyKind, PolicyOptions};
+        use clap::ValueEnum;
+
+        for kind in PolicyKind::value_variants() {
+            let mut dependent = JobConfig::new(0, 20, 0, 0);
+            dependent.set_dependencies(vec![0]);
+            let jobs = vec![
+                JobConfig::new(0, 10, 0, 0),
+                JobConfig::new(0, 50, 0, 0),
+                dependent,
+            ];
+
+            let policy = kind.create(&PolicyOptions::default());
+            let mut scheduler =
+                Scheduler::with_policy(SchedulerConfig::new(0, false, false), policy);
+            scheduler.set_trace(false);
+            scheduler.add_jobs(jobs);
+            for _ in 0..1000 {
+                if scheduler.is_finished() {
+                    break;
+                }
+                scheduler.run_tick();
+            }
+
+            assert!(scheduler.is_finished(), "{:?} never finished", kind);
+            assert_eq!(scheduler.finished_processes().len(), 3, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn proportional_share_report_with_spawned_children() {
+        use crate::config::Spawn;
+        use crate::policy::{PolicyKind, PolicyOptions};
+
+        for kind in [PolicyKind::Lottery, PolicyKind::Stride] {
+            let mut parent = JobConfig::new(0, 50, 0, 0);
+            parent.add_spawn(Spawn::parse("40/10").unwrap());
+
+            let policy = kind.create(&PolicyOptions::default());
+            let mut scheduler =
+                Scheduler::with_policy(SchedulerConfig::new(0, false, false), policy);
+            scheduler.set_trace(false);
+            scheduler.add_jobs(vec![JobConfig::new(0, 10, 0, 0), parent]);
+            scheduler.run_to_completion();
+
+            assert_eq!(scheduler.finished_processes().len(), 3, "{:?}", kind);
+            // The child arrives after the first job finished, outside the report's window
+            scheduler.policy().unwrap().report();
+        }
+    }
+
+    #[test]
+    fn snapshot_keeps_the_tasks() {
+        let mut periodic = JobConfig::new(0, 40, 0, 0);
+        periodic.set_period(100);
+        periodic.set_deadline(Some(50));
+        let mut dependent = JobConfig::new(0, 20, 0, 0);
+        dependent.set_dependencies(vec![0]);
+
+        let mut original = scheduler(Vec::new());
+        original.set_tasks(vec![periodic, dependent], vec![0, 0, 1]);
+        let copy = original.fork().unwrap();
+
+        assert_eq!(copy.task_configs()[0].period(), 100);
+        assert_eq!(copy.task_configs()[0].deadline(), Some(50));
+        assert_eq!(copy.task_configs()[1].dependencies(), [0]);
+        assert_eq!(copy.tasks(), [0, 0, 1]);
+    }
+
+    #[test]
+    fn preempted_rr_job_keeps_the_rest_of_its_slice() {
+        let rr = |arrival| {
+            let mut job = JobConfig::new(arrival, 300, 0, 0);
+            job.set_class(SchedClass::Rr, 5);
+            job
+        };
+        let mut fifo = JobConfig::new(50, 20, 0, 0);
+        fifo.set_class(SchedClass::Fifo, 9);
+
+        let mut scheduler = scheduler(vec![rr(0), rr(0), fifo]);
+        scheduler.run_to_completion();
+
+        let slices = scheduler
+            .slices()
+            .iter()
+            .take(4)
+            .map(|s| (s.pid(), s.start(), s.length()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            slices,
+            [(0, 0, 50), (2, 50, 20), (0, 70, 50), (1, 120, 100)]
+        );
+    }
+}
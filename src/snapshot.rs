@@ -0,0 +1,115 @@
+// snapshot.rs
+// Text format of a scheduler snapshot. Every line is a keyword followed by whitespace separated
+// fields, '-' stands for a missing value, and processes follow the queue they are in.
+// The types write and read their own lines, this module holds the shared pieces and file access.
+// Author: Hank Bao
+
+use std::fmt::Display;
+use std::str::SplitWhitespace;
+
+use clap::ValueEnum;
+
+use crate::scheduler::Scheduler;
+
+pub const HEADER: &str = "# mlfq snapshot";
+pub const VERSION: u32 = 1;
+
+// A missing value is written as '-'
+pub fn opt<T: Display>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |v| v.to_string())
+}
+
+// Name of a clap value enum, e.g. a discipline or a scheduling class
+pub fn name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map_or(String::new(), |v| v.get_name().to_string())
+}
+
+// The fields of one line after its keyword
+pub struct Fields<'a> {
+    tokens: SplitWhitespace<'a>,
+}
+
+impl<'a> Fields<'a> {
+    pub fn new(text: &'a str) -> Fields<'a> {
+        Fields {
+            tokens: text.split_whitespace(),
+        }
+    }
+
+    pub fn word(&mut self) -> Option<&'a str> {
+        self.tokens.next()
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.word()?.parse().ok()
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.word()?.parse().ok()
+    }
+
+    pub fn usize(&mut self) -> Option<usize> {
+        self.word()?.parse().ok()
+    }
+
+    pub fn bool(&mut self) -> Option<bool> {
+        self.word()?.parse().ok()
+    }
+
+    // Some(None) for '-'
+    pub fn opt_u32(&mut self) -> Option<Option<u32>> {
+        match self.word()? {
+            "-" => Some(None),
+            word => word.parse().ok().map(Some),
+        }
+    }
+
+    pub fn opt_usize(&mut self) -> Option<Option<usize>> {
+        match self.word()? {
+            "-" => Some(None),
+            word => word.parse().ok().map(Some),
+        }
+    }
+
+    pub fn value<T: ValueEnum>(&mut self) -> Option<T> {
+        T::from_str(self.word()?, false).ok()
+    }
+
+    // None if fields are left over
+    pub fn end(&mut self) -> Option<()> {
+        match self.tokens.next() {
+            Some(_) => None,
+            None => Some(()),
+        }
+    }
+}
+
+// Write the snapshot of the scheduler to a file, false if it cannot be taken or written
+pub fn save(scheduler: &Scheduler, path: &str) -> bool {
+    let text = match scheduler.snapshot() {
+        Some(text) => text,
+        None => return false,
+    };
+
+    match std::fs::write(path, text) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("Failed to write snapshot {}: {}", path, e);
+            false
+        }
+    }
+}
+
+pub fn load(path: &str) -> Option<Scheduler> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Failed to read snapshot {}: {}", path, e);
+            return None;
+        }
+    };
+
+    Scheduler::from_snapshot(&text)
+}
@@ -0,0 +1,559 @@
+// spool.rs
+// Job spooler daemon in the spirit of task-spooler. Clients send one request per connection over
+// a Unix socket, and the daemon runs the submitted shell commands under the MLFQ rules with a
+// Supervisor. The job list is saved to a state file whenever it changes, and jobs that had not
+// finished start again from their saved level when the daemon restarts.
+// Author: Hank Bao
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::{QueueConfig, SchedulerConfig};
+use crate::supervise::{JobState, Supervisor};
+
+const STATE_HEADER: &str = "# mlfq spool state: id state level cpu exit command";
+// How long a client may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub fn default_socket() -> PathBuf {
+    std::env::temp_dir().join("mlfq-spool.sock")
+}
+
+pub fn default_state() -> PathBuf {
+    std::env::temp_dir().join("mlfq-spool.state")
+}
+
+// Commands travel on a single line, so backslashes, tabs and newlines are escaped
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+// A single word is taken as a shell command line, several words as the arguments of one command
+pub fn shell_join(words: &[String]) -> String {
+    if words.len() == 1 {
+        return words[0].clone();
+    }
+
+    words
+        .iter()
+        .map(|w| {
+            let plain = !w.is_empty()
+                && w.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+            if plain {
+                w.clone()
+            } else {
+                format!("'{}'", w.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Send one request line and return the response. A response starting with "error:" is a failure.
+pub fn request(socket: &Path, line: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.write_all(line.as_bytes())?;
+    stream.write_all(b"\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum SpoolState {
+    Queued,
+    Running,
+    Blocked,
+    Done(Option<i32>),
+    Killed,
+}
+
+impl SpoolState {
+    fn name(&self) -> &'static str {
+        match self {
+            SpoolState::Queued => "queued",
+            SpoolState::Running => "running",
+            SpoolState::Blocked => "blocked",
+            SpoolState::Done(_) => "done",
+            SpoolState::Killed => "killed",
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        matches!(
+            self,
+            SpoolState::Queued | SpoolState::Running | SpoolState::Blocked
+        )
+    }
+}
+
+struct Record {
+    id: u32,
+    command: String,
+    state: SpoolState,
+    level: usize,
+    cpu: u64,
+    // The job in the supervisor, None for jobs that finished before a restart
+    index: Option<usize>,
+}
+
+// A connection whose request line has not fully arrived yet
+struct Client {
+    stream: UnixStream,
+    request: Vec<u8>,
+    accepted: Instant,
+}
+
+pub struct Spooler {
+    supervisor: Supervisor,
+    records: Vec<Record>,
+    clients: Vec<Client>,
+    levels: usize,
+    slots: usize,
+    state_path: PathBuf,
+    // The state file as last written
+    saved: String,
+    started: Instant,
+    shutdown: bool,
+}
+
+impl Spooler {
+    pub fn new(
+        config: SchedulerConfig,
+        queue_configs: Vec<QueueConfig>,
+        slots: usize,
+        state_path: PathBuf,
+    ) -> Spooler {
+        let levels = queue_configs.len();
+        // The daemon sleeps between rounds itself
+        let mut supervisor = Supervisor::new(config, queue_configs, Duration::ZERO);
+        supervisor.set_slots(slots);
+        supervisor.set_trace(false);
+
+        Spooler {
+            supervisor,
+            records: Vec::new(),
+            clients: Vec::new(),
+            levels,
+            slots,
+            state_path,
+            saved: String::new(),
+            started: Instant::now(),
+            shutdown: false,
+        }
+    }
+
+    pub fn set_trace(&mut self, trace: bool) {
+        self.supervisor.set_trace(trace);
+    }
+
+    // Load the state file, if there is one, and start its unfinished jobs again.
+    // Returns the number of restarted jobs.
+    pub fn restore(&mut self) -> io::Result<usize> {
+        let text = match fs::read_to_string(&self.state_path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let mut restarted = 0;
+        for (number, line) in text.lines().enumerate() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+
+            let mut record = match parse_record(line) {
+                Some(record) => record,
+                None => {
+                    eprintln!(
+                        "Skipped line {} of {}: {}",
+                        number + 1,
+                        self.state_path.display(),
+                        line
+                    );
+                    continue;
+                }
+            };
+
+            if record.state.is_active() {
+                let level = record.level.min(self.levels - 1);
+                let index = self.supervisor.spawn(&record.command)?;
+                self.supervisor.set_level(index, level);
+                record.state = SpoolState::Queued;
+                record.level = level;
+                record.cpu = 0;
+                record.index = Some(index);
+                restarted += 1;
+            }
+            self.records.push(record);
+        }
+
+        self.saved = text;
+        Ok(restarted)
+    }
+
+    // Accept requests and supervise the jobs until a shutdown request
+    pub fn serve(&mut self, socket: &Path, poll: Duration) -> io::Result<()> {
+        if let Ok(metadata) = fs::symlink_metadata(socket) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", socket.display()),
+                ));
+            }
+            if UnixStream::connect(socket).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("a daemon is already listening on {}", socket.display()),
+                ));
+            }
+            // Left behind by a daemon that did not exit cleanly
+            fs::remove_file(socket)?;
+        }
+
+        let listener = UnixListener::bind(socket)?;
+        listener.set_nonblocking(true)?;
+        self.save();
+
+        while !self.shutdown {
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => self.accept(stream),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        eprintln!("Failed to accept a client: {}", e);
+                        break;
+                    }
+                }
+            }
+            self.read_requests();
+
+            self.supervisor.step();
+            self.sync();
+            self.save();
+            std::thread::sleep(poll);
+        }
+
+        let _ = fs::remove_file(socket);
+        Ok(())
+    }
+
+    fn accept(&mut self, stream: UnixStream) {
+        if let Err(e) = stream.set_nonblocking(true) {
+            eprintln!("Failed to accept a client: {}", e);
+            return;
+        }
+        self.clients.push(Client {
+            stream,
+            request: Vec::new(),
+            accepted: Instant::now(),
+        });
+    }
+
+    // Read what the clients have sent without waiting, so a client that never finishes
+    // its request does not stall the jobs, and answer every complete request
+    fn read_requests(&mut self) {
+        for mut client in std::mem::take(&mut self.clients) {
+            match Self::read_request(&mut client) {
+                Ok(true) => self.handle(client),
+                Ok(false) if client.accepted.elapsed() < REQUEST_TIMEOUT => {
+                    self.clients.push(client)
+                }
+                Ok(false) => Self::reply(&client.stream, "error: request timed out\n"),
+                Err(e) => Self::reply(&client.stream, &format!("error: {}\n", e)),
+            }
+        }
+    }
+
+    // Read what has arrived, true once the request line is complete or the client closed
+    fn read_request(client: &mut Client) -> io::Result<bool> {
+        let mut buffer = [0u8; 1024];
+        loop {
+            match client.stream.read(&mut buffer) {
+                Ok(0) => return Ok(true),
+                Ok(n) => {
+                    client.request.extend_from_slice(&buffer[..n]);
+                    if client.request.contains(&b'\n') {
+                        return Ok(true);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn handle(&mut self, client: Client) {
+        let request = String::from_utf8_lossy(&client.request);
+        let line = request.lines().next().unwrap_or("");
+        let response = match self.respond(line) {
+            Ok(response) => response,
+            Err(e) => format!("error: {}\n", e),
+        };
+
+        Self::reply(&client.stream, &response);
+        self.sync();
+        self.save();
+    }
+
+    fn reply(mut stream: &UnixStream, response: &str) {
+        let _ = stream.set_nonblocking(false);
+        let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+        let _ = stream.write_all(response.as_bytes());
+    }
+
+    fn respond(&mut self, line: &str) -> Result<String, String> {
+        let (verb, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let args = rest.split_whitespace().collect::<Vec<&str>>();
+        let id = |i: usize| -> Result<u32, String> {
+            args.get(i)
+                .and_then(|s| s.parse::<u32>().ok())
+                .ok_or(format!("{} needs a job id", verb))
+        };
+
+        match verb {
+            "submit" => {
+                let command = unescape(rest);
+                if command.trim().is_empty() {
+                    return Err("submit needs a command".to_string());
+                }
+                let id = self.submit(&command).map_err(|e| e.to_string())?;
+                Ok(format!("Submitted job {}.\n", id))
+            }
+            "list" => Ok(self.list()),
+            "kill" => {
+                let id = id(0)?;
+                let index = self.active_index(id)?;
+                self.supervisor.kill(index);
+                Ok(format!("Killed job {}.\n", id))
+            }
+            "renice" => {
+                let id = id(0)?;
+                let level = args
+                    .get(1)
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .filter(|&l| l < self.levels)
+                    .ok_or(format!("renice needs a level below {}", self.levels))?;
+                let index = self.active_index(id)?;
+                self.supervisor.set_level(index, level);
+                Ok(format!("Moved job {} to level {}.\n", id, level))
+            }
+            "stats" => Ok(self.stats()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok("Shutting down, unfinished jobs start again on the next run.\n".to_string())
+            }
+            _ => Err(format!("unknown request {}", verb)),
+        }
+    }
+
+    fn submit(&mut self, command: &str) -> io::Result<u32> {
+        let id = self.records.iter().map(|r| r.id + 1).max().unwrap_or(1);
+        let index = self.supervisor.spawn(command)?;
+        self.records.push(Record {
+            id,
+            command: command.to_string(),
+            state: SpoolState::Queued,
+            level: 0,
+            cpu: 0,
+            index: Some(index),
+        });
+        Ok(id)
+    }
+
+    fn active_index(&self, id: u32) -> Result<usize, String> {
+        let record = self
+            .records
+            .iter()
+            .find(|r| r.id == id)
+            .ok_or(format!("no job {}", id))?;
+        match record.index {
+            Some(index) if record.state.is_active() => Ok(index),
+            _ => Err(format!("job {} has already finished", id)),
+        }
+    }
+
+    // Copy the state of the supervised jobs into the records
+    fn sync(&mut self) {
+        let jobs = self.supervisor.jobs();
+        for record in self.records.iter_mut() {
+            let job = match record.index {
+                Some(index) => &jobs[index],
+                None => continue,
+            };
+
+            record.level = job.level();
+            record.cpu = job.cpu();
+            record.state = match job.state() {
+                JobState::Ready => SpoolState::Queued,
+                JobState::Running => SpoolState::Running,
+                JobState::Blocked => SpoolState::Blocked,
+                JobState::Finished if job.killed() => SpoolState::Killed,
+                JobState::Finished => SpoolState::Done(job.exit_code()),
+            };
+        }
+    }
+
+    // Write the state file if it changed, through a temporary file so it is never half written
+    fn save(&mut self) {
+        let mut text = String::from(STATE_HEADER);
+        text.push('\n');
+        for record in self.records.iter() {
+            let exit = match record.state {
+                SpoolState::Done(Some(code)) => code.to_string(),
+                _ => "-".to_string(),
+            };
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\n",
+                record.id,
+                record.state.name(),
+                record.level,
+                record.cpu,
+                exit,
+                escape(&record.command)
+            ));
+        }
+
+        if text == self.saved {
+            return;
+        }
+
+        let temp = self.state_path.with_extension("tmp");
+        let result = fs::write(&temp, &text).and_then(|_| fs::rename(&temp, &self.state_path));
+        match result {
+            Ok(_) => self.saved = text,
+            Err(e) => eprintln!(
+                "Failed to save the state to {}: {}",
+                self.state_path.display(),
+                e
+            ),
+        }
+    }
+
+    fn list(&self) -> String {
+        let jobs = self.supervisor.jobs();
+        let mut out = format!(
+            "{:>5} {:>8} {:>5} {:>8} {:>7} {:>5}  command\n",
+            "id", "state", "level", "cpu", "pid", "exit"
+        );
+        for record in self.records.iter() {
+            let pid = match record.index {
+                Some(index) if record.state.is_active() => jobs[index].pid().to_string(),
+                _ => "-".to_string(),
+            };
+            let exit = match record.state {
+                SpoolState::Done(Some(code)) => code.to_string(),
+                _ => "-".to_string(),
+            };
+            out.push_str(&format!(
+                "{:>5} {:>8} {:>5} {:>8} {:>7} {:>5}  {}\n",
+                record.id,
+                record.state.name(),
+                record.level,
+                record.cpu,
+                pid,
+                exit,
+                record.command.replace('\n', "\\n").replace('\t', "\\t")
+            ));
+        }
+        out
+    }
+
+    fn stats(&self) -> String {
+        let count = |state: &str| {
+            self.records
+                .iter()
+                .filter(|r| r.state.name() == state)
+                .count()
+        };
+        let per_level = (0..self.levels)
+            .map(|level| {
+                self.records
+                    .iter()
+                    .filter(|r| r.state.is_active() && r.level == level)
+                    .count()
+                    .to_string()
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        let failed = self
+            .records
+            .iter()
+            .filter(|r| matches!(r.state, SpoolState::Done(code) if code != Some(0)))
+            .count();
+
+        format!(
+            "Up for {} s with {} slots.\n\
+             Jobs: {} queued, {} running, {} blocked, {} done ({} failed), {} killed.\n\
+             Active jobs per level: {}.\n\
+             CPU time of all jobs: {} ms.\n",
+            self.started.elapsed().as_secs(),
+            self.slots,
+            count("queued"),
+            count("running"),
+            count("blocked"),
+            count("done"),
+            failed,
+            count("killed"),
+            per_level,
+            self.records.iter().map(|r| r.cpu).sum::<u64>()
+        )
+    }
+}
+
+fn parse_record(line: &str) -> Option<Record> {
+    let mut fields = line.splitn(6, '\t');
+    let id = fields.next()?.parse::<u32>().ok()?;
+    let state = fields.next()?;
+    let level = fields.next()?.parse::<usize>().ok()?;
+    let cpu = fields.next()?.parse::<u64>().ok()?;
+    let exit = match fields.next()? {
+        "-" => None,
+        code => Some(code.parse::<i32>().ok()?),
+    };
+    let command = unescape(fields.next()?);
+
+    let state = match state {
+        "queued" => SpoolState::Queued,
+        "running" => SpoolState::Running,
+        "blocked" => SpoolState::Blocked,
+        "done" => SpoolState::Done(exit),
+        "killed" => SpoolState::Killed,
+        _ => return None,
+    };
+
+    Some(Record {
+        id,
+        command,
+        state,
+        level,
+        cpu,
+        index: None,
+    })
+}
@@ -0,0 +1,552 @@
+// supervise.rs
+// Supervisor that applies the MLFQ rules to real child processes. Every command starts stopped,
+// and only the chosen ones, one per slot, are continued with SIGCONT until their quantum ends
+// or they block.
+// CPU time comes from /proc/<pid>/stat of every process in the group of a command, and a group
+// where no process is runnable is treated as blocked on I/O.
+// Author: Hank Bao
+
+use std::collections::VecDeque;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+
+use crate::config::{JobConfig, QueueConfig, SchedulerConfig};
+use crate::scheduler::Scheduler;
+
+const SIGKILL: i32 = 9;
+const SIGCONT: i32 = 18;
+const SIGSTOP: i32 = 19;
+const SC_CLK_TCK: i32 = 2;
+
+extern "C" {
+    fn kill(pid: i32, sig: i32) -> i32;
+    fn sysconf(name: i32) -> i64;
+}
+
+// Signal the whole process group of the command, so pipelines stop and continue together
+fn signal(pgid: u32, sig: i32) {
+    unsafe {
+        kill(-(pgid as i32), sig);
+    }
+}
+
+// State letter, process group and CPU time in milliseconds from /proc/<pid>/stat,
+// None once the process is gone. The CPU time includes the children it has waited for.
+fn proc_stat(pid: u32, clk_tck: u64) -> Option<(char, u32, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+    // The command name may contain spaces, so split after its closing parenthesis
+    let rest = stat.get(stat.rfind(')')? + 2..)?;
+    let fields = rest.split_whitespace().collect::<Vec<&str>>();
+    let state = fields.first()?.chars().next()?;
+    let pgrp = fields.get(2)?.parse::<u32>().ok()?;
+    // utime, stime, cutime and cstime are fields 14 to 17 of the file, 11 to 14 after the state
+    let ticks = fields
+        .get(11..15)?
+        .iter()
+        .map(|f| f.parse::<u64>().ok())
+        .sum::<Option<u64>>()?;
+
+    Some((state, pgrp, ticks * 1000 / clk_tck))
+}
+
+// State letter and CPU time of a whole process group, e.g. a pipeline or make and its jobs.
+// The group is runnable if any member is, and sleeping only if no member is runnable.
+// None once no process of the group is left.
+fn group_stat(pgid: u32, clk_tck: u64) -> Option<(char, u64)> {
+    let rank = |state: char| match state {
+        'R' => 2,
+        'S' | 'D' => 1,
+        _ => 0,
+    };
+
+    let mut group = None;
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let (state, cpu) = match proc_stat(pid, clk_tck) {
+            Some((state, pgrp, cpu)) if pgrp == pgid => (state, cpu),
+            _ => continue,
+        };
+
+        group = match group {
+            Some((s, total)) if rank(s) >= rank(state) => Some((s, total + cpu)),
+            Some((_, total)) => Some((state, total + cpu)),
+            None => Some((state, cpu)),
+        };
+    }
+    group
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum JobState {
+    Ready,
+    Running,
+    Blocked,
+    Finished,
+}
+
+pub struct Job {
+    command: String,
+    child: Child,
+    pid: u32,
+    state: JobState,
+    level: usize,
+    allotment: u64,
+    // CPU time in milliseconds, as of the last time it was read
+    cpu: u64,
+    first_run: Option<u64>,
+    finish: Option<u64>,
+    blocked_since: u64,
+    blocks: u32,
+    blocked_total: u64,
+    demotions: u32,
+    // Exit code, None while running or if it was ended by a signal
+    exit_code: Option<i32>,
+    killed: bool,
+}
+
+impl Job {
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn state(&self) -> JobState {
+        self.state
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    // CPU time in milliseconds
+    pub fn cpu(&self) -> u64 {
+        self.cpu
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    pub fn killed(&self) -> bool {
+        self.killed
+    }
+}
+
+pub struct Supervisor {
+    config: SchedulerConfig,
+    queue_configs: Vec<QueueConfig>,
+    jobs: Vec<Job>,
+    queues: Vec<VecDeque<usize>>,
+    // (job, start of the slice in milliseconds, CPU time at the start) for every busy slot
+    running: Vec<(usize, u64, u64)>,
+    slots: usize,
+    poll: Duration,
+    clk_tck: u64,
+    epoch: Instant,
+    last_boost: u64,
+    trace: bool,
+}
+
+impl Supervisor {
+    pub fn new(config: SchedulerConfig, queue_configs: Vec<QueueConfig>, poll: Duration) -> Self {
+        assert!(!queue_configs.is_empty());
+        let clk_tck = unsafe { sysconf(SC_CLK_TCK) }.max(1) as u64;
+
+        Supervisor {
+            queues: vec![VecDeque::new(); queue_configs.len()],
+            config,
+            queue_configs,
+            jobs: Vec::new(),
+            running: Vec::new(),
+            slots: 1,
+            poll,
+            clk_tck,
+            epoch: Instant::now(),
+            last_boost: 0,
+            trace: true,
+        }
+    }
+
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    // How many commands may run at the same time
+    pub fn set_slots(&mut self, slots: usize) {
+        assert!(slots > 0);
+        self.slots = slots;
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.jobs.iter().all(|j| j.state == JobState::Finished)
+    }
+
+    // Start the command stopped in its own process group. It enters the highest queue (Rule 3).
+    pub fn spawn(&mut self, command: &str) -> std::io::Result<usize> {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg("kill -STOP $$; exec sh -c \"$0\"")
+            .arg(command)
+            .process_group(0)
+            .spawn()?;
+        let pid = child.id();
+
+        // Wait until the shell has stopped itself, so no command runs before it is picked
+        while proc_stat(pid, self.clk_tck).is_some_and(|(state, _, _)| state != 'T') {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let index = self.jobs.len();
+        self.jobs.push(Job {
+            command: command.to_string(),
+            child,
+            pid,
+            state: JobState::Ready,
+            level: 0,
+            allotment: self.queue_configs[0].allotment() as u64,
+            cpu: 0,
+            first_run: None,
+            finish: None,
+            blocked_since: 0,
+            blocks: 0,
+            blocked_total: 0,
+            demotions: 0,
+            exit_code: None,
+            killed: false,
+        });
+        self.queues[0].push_back(index);
+
+        if self.trace {
+            println!("[{}:<S>] Process {} started: {}", self.now(), pid, command);
+        }
+
+        Ok(index)
+    }
+
+    // Supervise until every command has exited
+    pub fn run(&mut self) {
+        while !self.is_finished() {
+            self.step();
+            std::thread::sleep(self.poll);
+        }
+    }
+
+    // One round of supervision: collect exits and wakeups, boost, end slices and fill the slots
+    pub fn step(&mut self) {
+        let now = self.now();
+        self.reap(now);
+        self.wake_blocked(now);
+
+        let interval = self.config.priority_boost_interval() as u64;
+        if interval > 0 && now - self.last_boost >= interval {
+            self.boost(now);
+        }
+
+        for (index, start, cpu_start) in std::mem::take(&mut self.running) {
+            if self.check_running(index, start, cpu_start, now) {
+                self.running.push((index, start, cpu_start));
+            }
+        }
+
+        while self.running.len() < self.slots && self.pick(now) {}
+    }
+
+    // Kill the command and its process group, false if it has already exited
+    pub fn kill(&mut self, index: usize) -> bool {
+        let job = match self.jobs.get_mut(index) {
+            Some(job) if job.state != JobState::Finished => job,
+            _ => return false,
+        };
+
+        job.killed = true;
+        signal(job.pid, SIGKILL);
+        signal(job.pid, SIGCONT);
+        true
+    }
+
+    // Move the command to another queue with a full allotment, false if it has already exited
+    pub fn set_level(&mut self, index: usize, level: usize) -> bool {
+        assert!(level < self.queues.len());
+        let allotment = self.queue_configs[level].allotment() as u64;
+        let job = match self.jobs.get_mut(index) {
+            Some(job) if job.state != JobState::Finished => job,
+            _ => return false,
+        };
+
+        let old = job.level;
+        job.level = level;
+        job.allotment = allotment;
+        if job.state == JobState::Ready {
+            self.queues[old].retain(|&i| i != index);
+            self.queues[level].push_back(index);
+        }
+        true
+    }
+
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+
+    fn read_cpu(&mut self, index: usize) -> Option<char> {
+        let job = &mut self.jobs[index];
+        let (state, cpu) = group_stat(job.pid, self.clk_tck)?;
+        job.cpu = cpu;
+        Some(state)
+    }
+
+    // Collect the commands that exited
+    fn reap(&mut self, now: u64) {
+        for index in 0..self.jobs.len() {
+            if self.jobs[index].state == JobState::Finished {
+                continue;
+            }
+
+            // The CPU time cannot be read after the process has been waited for
+            self.read_cpu(index);
+            let job = &mut self.jobs[index];
+            let status = match job.child.try_wait() {
+                Ok(Some(status)) => status,
+                _ => continue,
+            };
+
+            // Remaining processes of the group must not be left stopped
+            signal(job.pid, SIGCONT);
+            job.state = JobState::Finished;
+            job.exit_code = status.code();
+            job.finish = Some(now);
+            for queue in self.queues.iter_mut() {
+                queue.retain(|&i| i != index);
+            }
+            self.running.retain(|&(i, _, _)| i != index);
+
+            if self.trace {
+                println!(
+                    "[{}:<S>] Process {} finished. CPU time: {}.",
+                    now, job.pid, job.cpu
+                );
+            }
+        }
+    }
+
+    // A blocked command that is runnable again is stopped and queued
+    fn wake_blocked(&mut self, now: u64) {
+        for index in 0..self.jobs.len() {
+            if self.jobs[index].state != JobState::Blocked {
+                continue;
+            }
+
+            if self.read_cpu(index) != Some('R') {
+                continue;
+            }
+
+            let job = &mut self.jobs[index];
+            signal(job.pid, SIGSTOP);
+            job.state = JobState::Ready;
+            job.blocked_total += now - job.blocked_since;
+
+            if self.config.io_bump() {
+                self.queues[job.level].push_front(index);
+            } else {
+                self.queues[job.level].push_back(index);
+            }
+
+            if self.trace {
+                println!(
+                    "[{}:<{}>] Process {} returned from I/O.",
+                    now, job.level, job.pid
+                );
+            }
+        }
+    }
+
+    // Rule 5: move every command to the highest queue
+    fn boost(&mut self, now: u64) {
+        self.last_boost = now;
+        for queue in self.queues.iter_mut() {
+            queue.clear();
+        }
+
+        let allotment = self.queue_configs[0].allotment() as u64;
+        for (index, job) in self.jobs.iter_mut().enumerate() {
+            if job.state == JobState::Finished {
+                continue;
+            }
+
+            job.level = 0;
+            job.allotment = allotment;
+            if job.state == JobState::Ready {
+                self.queues[0].push_back(index);
+            }
+        }
+
+        if self.trace {
+            println!("[{}:<S>] Priority boost.", now);
+        }
+    }
+
+    // End the slice when the command sleeps or its quantum is up, true if it keeps running
+    fn check_running(&mut self, index: usize, start: u64, cpu_start: u64, now: u64) -> bool {
+        let state = match self.read_cpu(index) {
+            Some(state) => state,
+            None => return true,
+        };
+
+        let blocked = matches!(state, 'S' | 'D');
+        let level = self.jobs[index].level;
+        if !blocked && now - start < self.queue_configs[level].quantum() as u64 {
+            return true;
+        }
+
+        let job = &mut self.jobs[index];
+        let used = job.cpu - cpu_start;
+        job.allotment = job.allotment.saturating_sub(used);
+
+        if blocked {
+            // Leave it continued, so it is seen as soon as it becomes runnable again
+            job.state = JobState::Blocked;
+            job.blocked_since = now;
+            job.blocks += 1;
+            if self.trace {
+                println!(
+                    "[{}:<{}>] Process {} has run for {}, then blocked.",
+                    now, level, job.pid, used
+                );
+            }
+        } else {
+            signal(job.pid, SIGSTOP);
+            job.state = JobState::Ready;
+            if self.trace {
+                println!(
+                    "[{}:<{}>] Process {} has run for {}.",
+                    now, level, job.pid, used
+                );
+            }
+        }
+
+        let io_stay = blocked && self.config.io_stay();
+        if io_stay {
+            job.allotment = self.queue_configs[level].allotment() as u64;
+        } else if job.allotment == 0 && level + 1 < self.queues.len() {
+            // Rule 4: the allotment is used up
+            job.level += 1;
+            job.allotment = self.queue_configs[job.level].allotment() as u64;
+            job.demotions += 1;
+            if self.trace {
+                println!(
+                    "[{}:<S>] Process {} priority reduced to {}.",
+                    now, job.pid, job.level
+                );
+            }
+        } else if job.allotment == 0 {
+            job.allotment = self.queue_configs[level].allotment() as u64;
+        }
+
+        if job.state == JobState::Ready {
+            self.queues[job.level].push_back(index);
+        }
+        false
+    }
+
+    // Rules 1 and 2: continue the first command of the highest non-empty queue, false if none is ready
+    fn pick(&mut self, now: u64) -> bool {
+        let index = match self.queues.iter_mut().find_map(|q| q.pop_front()) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        self.read_cpu(index);
+        let job = &mut self.jobs[index];
+        job.state = JobState::Running;
+        job.first_run.get_or_insert(now);
+        signal(job.pid, SIGCONT);
+        self.running.push((index, now, job.cpu));
+
+        if self.trace {
+            println!("[{}:<{}>] Process {} running.", now, job.level, job.pid);
+        }
+        true
+    }
+
+    // Each command as a simulated job, with the CPU time as workload and its I/O spread evenly
+    pub fn estimated_jobs(&self) -> Vec<JobConfig> {
+        self.jobs
+            .iter()
+            .map(|job| {
+                let workload = job.cpu.max(1) as u32;
+                let (io_interval, io_length) = match job.blocks {
+                    0 => (0, 0),
+                    blocks => (
+                        (workload / (blocks + 1)).max(1),
+                        (job.blocked_total / blocks as u64) as u32,
+                    ),
+                };
+                JobConfig::new(0, workload, io_interval, io_length)
+            })
+            .collect()
+    }
+
+    // What happened to every command, next to what the simulator predicts for the same jobs
+    pub fn print_report(&self) {
+        let mut scheduler = Scheduler::new(self.config, self.queue_configs.clone());
+        scheduler.set_trace(false);
+        scheduler.add_jobs(self.estimated_jobs());
+        scheduler.run_to_completion();
+
+        let mut predicted = scheduler
+            .finished_processes()
+            .iter()
+            .map(|p| (p.pid(), p.turnaround_time(), p.response_time()))
+            .collect::<Vec<_>>();
+        predicted.sort_unstable();
+
+        println!("All processes finished. Times are in milliseconds.");
+        println!(
+            "{:>7} {:>7} {:>6} {:>6} {:>10} {:>10} {:>10} {:>10}  command",
+            "pid", "cpu", "level", "blocks", "response", "predicted", "turnaround", "predicted"
+        );
+        for (i, job) in self.jobs.iter().enumerate() {
+            let (_, turnaround, response) = predicted[i];
+            println!(
+                "{:>7} {:>7} {:>6} {:>6} {:>10} {:>10} {:>10} {:>10}  {}",
+                job.pid,
+                job.cpu,
+                job.level,
+                job.blocks,
+                job.first_run.unwrap_or(0),
+                response,
+                job.finish.unwrap_or(0),
+                turnaround,
+                job.command
+            );
+        }
+    }
+}
+
+impl Drop for Supervisor {
+    // Never leave stopped children behind
+    fn drop(&mut self) {
+        for job in self.jobs.iter_mut() {
+            if job.state != JobState::Finished {
+                signal(job.pid, SIGKILL);
+                signal(job.pid, SIGCONT);
+                let _ = job.child.wait();
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+// sweep.rs
+// Parameter sweep over scheduler and queue configurations.
+// Author: Hank Bao
+
+use std::thread;
+
+use crate::config::{JobConfig, QueueConfig, SchedulerConfig};
+use crate::metrics::Metrics;
+use crate::scheduler::Scheduler;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum Objective {
+    Turnaround,
+    Response,
+    Idle,
+    Makespan,
+}
+
+impl Objective {
+    pub fn score(&self, metrics: &Metrics) -> u32 {
+        match self {
+            Objective::Turnaround => metrics.average_turnaround_time(),
+            Objective::Response => metrics.average_response_time(),
+            Objective::Idle => metrics.idle_time(),
+            Objective::Makespan => metrics.makespan(),
+        }
+    }
+}
+
+// The candidate values of every dimension of the sweep.
+// quantums and allotments hold one candidate list per queue level.
+pub struct SweepSpace {
+    quantums: Vec<Vec<u32>>,
+    allotments: Vec<Vec<u32>>,
+    push_fronts: Vec<bool>,
+    boosts: Vec<u32>,
+    io_bumps: Vec<bool>,
+    io_stays: Vec<bool>,
+}
+
+impl SweepSpace {
+    pub fn new(
+        quantums: Vec<Vec<u32>>,
+        allotments: Vec<Vec<u32>>,
+        push_fronts: Vec<bool>,
+        boosts: Vec<u32>,
+        io_bumps: Vec<bool>,
+        io_stays: Vec<bool>,
+    ) -> SweepSpace {
+        assert_eq!(quantums.len(), allotments.len());
+        SweepSpace {
+            quantums,
+            allotments,
+            push_fronts,
+            boosts,
+            io_bumps,
+            io_stays,
+        }
+    }
+
+    fn dimensions(&self) -> Vec<usize> {
+        let mut dims = Vec::new();
+        dims.extend(self.quantums.iter().map(|q| q.len()));
+        dims.extend(self.allotments.iter().map(|a| a.len()));
+        dims.push(self.push_fronts.len());
+        dims.push(self.boosts.len());
+        dims.push(self.io_bumps.len());
+        dims.push(self.io_stays.len());
+        dims
+    }
+
+    // Number of combinations in the space, None if it does not fit in a usize
+    pub fn size(&self) -> Option<usize> {
+        self.dimensions()
+            .into_iter()
+            .try_fold(1usize, |acc, d| acc.checked_mul(d))
+    }
+
+    // Decode the combination at the given index, treating the dimensions as a mixed-radix number
+    fn combination(&self, mut index: usize) -> (SchedulerConfig, Vec<QueueConfig>) {
+        let mut pick = |len: usize| {
+            let i = index % len;
+            index /= len;
+            i
+        };
+
+        let levels = self.quantums.len();
+        let quantums = (0..levels)
+            .map(|l| self.quantums[l][pick(self.quantums[l].len())])
+            .collect::<Vec<u32>>();
+        let allotments = (0..levels)
+            .map(|l| self.allotments[l][pick(self.allotments[l].len())])
+            .collect::<Vec<u32>>();
+        let push_front = self.push_fronts[pick(self.push_fronts.len())];
+        let boost = self.boosts[pick(self.boosts.len())];
+        let io_bump = self.io_bumps[pick(self.io_bumps.len())];
+        let io_stay = self.io_stays[pick(self.io_stays.len())];
+
+        let queue_configs = std::iter::zip(quantums, allotments)
+            .map(|(quantum, allotment)| QueueConfig::new(quantum, allotment, push_front))
+            .collect::<Vec<QueueConfig>>();
+
+        (SchedulerConfig::new(boost, io_bump, io_stay), queue_configs)
+    }
+}
+
+pub struct SweepResult {
+    scheduler_config: SchedulerConfig,
+    queue_configs: Vec<QueueConfig>,
+    metrics: Metrics,
+}
+
+// Run the workload quietly under the given configuration and collect its metrics
+pub fn simulate(
+    scheduler_config: SchedulerConfig,
+    queue_configs: Vec<QueueConfig>,
+    jobs: &[JobConfig],
+) -> Metrics {
+    let mut scheduler = Scheduler::new(scheduler_config, queue_configs);
+    scheduler.set_trace(false);
+    scheduler.add_jobs(jobs.to_vec());
+    scheduler.run_to_completion();
+
+    Metrics::from(&scheduler)
+}
+
+// Run every combination of the space on `threads` worker threads.
+// Results are sorted by the objective, ties keep the enumeration order.
+pub fn run_sweep(
+    space: &SweepSpace,
+    jobs: &[JobConfig],
+    threads: usize,
+    objective: Objective,
+) -> Vec<SweepResult> {
+    let total = space.size().expect("sweep space too large");
+    let threads = threads.clamp(1, total.max(1));
+
+    let mut results = thread::scope(|s| {
+        let workers = (0..threads)
+            .map(|t| {
+                s.spawn(move || {
+                    (t..total)
+                        .step_by(threads)
+                        .map(|i| {
+                            let (scheduler_config, queue_configs) = space.combination(i);
+                            let metrics = simulate(scheduler_config, queue_configs.clone(), jobs);
+                            (
+                                i,
+                                SweepResult {
+                                    scheduler_config,
+                                    queue_configs,
+                                    metrics,
+                                },
+                            )
+                        })
+                        .collect::<Vec<(usize, SweepResult)>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("sweep worker panicked"))
+            .collect::<Vec<(usize, SweepResult)>>()
+    });
+
+    results.sort_by_key(|(i, r)| (objective.score(&r.metrics), *i));
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+pub fn print_table(results: &[SweepResult], limit: usize) {
+    println!(
+        "{:>5}  {:<20} {:<20} {:>5} {:>6} {:>5} {:>5} {:>10} {:>9} {:>6} {:>8}",
+        "rank",
+        "quantums",
+        "allotments",
+        "front",
+        "boost",
+        "bump",
+        "stay",
+        "turnaround",
+        "response",
+        "idle",
+        "makespan"
+    );
+
+    let limit = if limit == 0 { results.len() } else { limit };
+    for (rank, result) in results.iter().take(limit).enumerate() {
+        let config = result.scheduler_config;
+        let metrics = &result.metrics;
+        println!(
+            "{:>5}  {:<20} {:<20} {:>5} {:>6} {:>5} {:>5} {:>10} {:>9} {:>6} {:>8}",
+            rank + 1,
+            join_values(result.queue_configs.iter().map(|q| q.quantum())),
+            join_values(result.queue_configs.iter().map(|q| q.allotment())),
+            result.queue_configs.first().is_some_and(|q| q.push_front()),
+            config.priority_boost_interval(),
+            config.io_bump(),
+            config.io_stay(),
+            metrics.average_turnaround_time(),
+            metrics.average_response_time(),
+            metrics.idle_time(),
+            metrics.makespan()
+        );
+    }
+}
+
+fn join_values(values: impl Iterator<Item = u32>) -> String {
+    values
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// Parse a list of value specs, one per queue level, separated by ','.
+// Quantums and allotments must be positive, so zero is rejected.
+pub fn parse_level_specs(specs: &str) -> Option<Vec<Vec<u32>>> {
+    specs
+        .split(',')
+        .map(|spec| parse_value_spec(spec).filter(|values| !values.contains(&0)))
+        .collect()
+}
+
+// Parse a value spec in the form of alternatives separated by '|'.
+// Each alternative is a single value x, a range lo-hi, or a stepped range lo-hi/step.
+pub fn parse_value_spec(spec: &str) -> Option<Vec<u32>> {
+    let mut values = Vec::new();
+
+    for alt in spec.split('|') {
+        let (range, step) = match alt.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok()?),
+            None => (alt, 1),
+        };
+
+        match range.split_once('-') {
+            Some((lo, hi)) => {
+                let lo = lo.parse::<u32>().ok()?;
+                let hi = hi.parse::<u32>().ok()?;
+                if step == 0 || lo > hi {
+                    return None;
+                }
+                values.extend((lo..=hi).step_by(step as usize));
+            }
+            None => values.push(range.parse::<u32>().ok()?),
+        }
+    }
+
+    Some(values)
+}
+
+// Parse a boolean spec: true, false, or both
+pub fn parse_bool_spec(spec: &str) -> Option<Vec<bool>> {
+    match spec {
+        "true" => Some(vec![true]),
+        "false" => Some(vec![false]),
+        "both" => Some(vec![false, true]),
+        _ => None,
+    }
+}
@@ -0,0 +1,107 @@
+// tree.rs
+// Process trees of jobs that spawn children, with the metrics of every tree attributed
+// to its root, to see how the MLFQ treats a parent and the children it spawns.
+// Author: Hank Bao
+
+use crate::process::Process;
+use crate::scheduler::Scheduler;
+
+// A job that is not a child, with every descendant it spawned
+pub struct ProcessTree {
+    root: u32,
+    descendants: Vec<u32>,
+}
+
+impl ProcessTree {
+    pub fn root(&self) -> u32 {
+        self.root
+    }
+
+    pub fn descendants(&self) -> &[u32] {
+        &self.descendants
+    }
+}
+
+// The trees of the roots that spawned at least one child, by root pid
+pub fn trees(scheduler: &Scheduler) -> Vec<ProcessTree> {
+    let children = scheduler.children();
+    let mut roots = children
+        .iter()
+        .map(|&(parent, _)| parent)
+        .filter(|&parent| children.iter().all(|&(_, child)| child != parent))
+        .collect::<Vec<u32>>();
+    roots.sort_unstable();
+    roots.dedup();
+
+    roots
+        .into_iter()
+        .map(|root| {
+            let mut descendants = Vec::new();
+            let mut next = vec![root];
+            while let Some(parent) = next.pop() {
+                for &(_, child) in children.iter().filter(|&&(p, _)| p == parent) {
+                    descendants.push(child);
+                    next.push(child);
+                }
+            }
+            descendants.sort_unstable();
+            ProcessTree { root, descendants }
+        })
+        .collect()
+}
+
+pub fn print_report(scheduler: &Scheduler) {
+    let finished = |pid: u32| -> Option<&Process> {
+        scheduler
+            .finished_processes()
+            .iter()
+            .find(|p| p.pid() == pid)
+    };
+
+    println!("Process trees, children attributed to their root:");
+    println!(
+        "{:>5} {:>9} {:>9} {:>11} {:>16} {:>16}",
+        "root", "children", "cpu time", "turnaround", "tree turnaround", "child response"
+    );
+    for tree in trees(scheduler) {
+        let members = std::iter::once(tree.root())
+            .chain(tree.descendants().iter().copied())
+            .map(finished)
+            .collect::<Option<Vec<&Process>>>();
+        let root = finished(tree.root());
+
+        // A tree with a killed or unfinished member has no tree metrics
+        let (cpu, tree_turnaround, child_response) = match (members, root) {
+            (Some(members), Some(root)) => {
+                let end = members
+                    .iter()
+                    .map(|p| p.start_time() + p.turnaround_time())
+                    .max()
+                    .unwrap_or(0);
+                let children = &members[1..];
+                let response = children.iter().map(|p| p.response_time()).sum::<u32>()
+                    / children.len().max(1) as u32;
+                (
+                    members
+                        .iter()
+                        .map(|p| p.workload())
+                        .sum::<u32>()
+                        .to_string(),
+                    (end - root.start_time()).to_string(),
+                    response.to_string(),
+                )
+            }
+            _ => ("-".to_string(), "-".to_string(), "-".to_string()),
+        };
+
+        println!(
+            "{:>5} {:>9} {:>9} {:>11} {:>16} {:>16}",
+            tree.root(),
+            tree.descendants().len(),
+            cpu,
+            root.map_or("-".to_string(), |p| p.turnaround_time().to_string()),
+            tree_turnaround,
+            child_response
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// whatif.rs
+// What-if branching: run a workload to some time, fork the scheduler there, change a setting
+// of one copy and run both to the end, so the difference comes from the change alone.
+// Author: Hank Bao
+
+use clap::ValueEnum;
+
+use crate::compare::{self, Outcome};
+use crate::config::{Discipline, QueueConfig, SchedulerConfig};
+use crate::scheduler::Scheduler;
+
+// A setting changed at the fork, given as KEY=VALUE
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Change {
+    Boost(u32),
+    IoBump(bool),
+    IoStay(bool),
+    Quantums(Vec<u32>),
+    Allotments(Vec<u32>),
+    PushFront(bool),
+    Aging(Vec<u32>),
+    Disciplines(Vec<Discipline>),
+}
+
+impl Change {
+    // Per-level settings need a queue per value, the other settings apply to any scheduler
+    fn levels(&self) -> Option<usize> {
+        match self {
+            Change::Quantums(v) | Change::Allotments(v) | Change::Aging(v) => Some(v.len()),
+            Change::Disciplines(v) => Some(v.len()),
+            _ => None,
+        }
+    }
+}
+
+// Parse boost=n, bump=true|false, stay=true|false, q=x,y,z, a=x,y,z, front=true|false,
+// aging=x,y,z or discipline=x,y,z
+pub fn parse_change(item: &str) -> Option<Change> {
+    let (key, value) = match item.split_once('=') {
+        Some(pair) => pair,
+        None => {
+            eprintln!("A change must be in the form KEY=VALUE, found {}.", item);
+            return None;
+        }
+    };
+
+    let positive = |value: &str| {
+        value
+            .split(',')
+            .map(|x| x.trim().parse::<u32>().ok().filter(|&x| x > 0))
+            .collect::<Option<Vec<u32>>>()
+    };
+    let change = match key.trim() {
+        "boost" | "b" => value.parse::<u32>().ok().map(Change::Boost),
+        "bump" => value.parse::<bool>().ok().map(Change::IoBump),
+        "stay" => value.parse::<bool>().ok().map(Change::IoStay),
+        "front" => value.parse::<bool>().ok().map(Change::PushFront),
+        "q" | "quantum" => positive(value).map(Change::Quantums),
+        "a" | "allotment" => positive(value).map(Change::Allotments),
+        "aging" => value
+            .split(',')
+            .map(|x| x.trim().parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()
+            .map(Change::Aging),
+        "discipline" => value
+            .split(',')
+            .map(|x| Discipline::from_str(x.trim(), true).ok())
+            .collect::<Option<Vec<Discipline>>>()
+            .map(Change::Disciplines),
+        _ => {
+            eprintln!(
+                "Unknown setting {}. Use boost, bump, stay, q, a, front, aging or discipline.",
+                key
+            );
+            return None;
+        }
+    };
+
+    if change.is_none() {
+        eprintln!("Invalid value {} for {}.", value, key);
+    }
+    change
+}
+
+// Apply the changes to a running scheduler, false if they do not fit it
+pub fn apply(scheduler: &mut Scheduler, changes: &[Change]) -> bool {
+    let levels = scheduler.queues().len();
+    for change in changes.iter() {
+        match change.levels() {
+            Some(_) if scheduler.dispatch_table().is_some() => {
+                eprintln!("The queues of a dispatch table run cannot be changed.");
+                return false;
+            }
+            Some(n) if n != levels => {
+                eprintln!("Per-level settings need {} values, one per queue.", levels);
+                return false;
+            }
+            _ => {}
+        }
+    }
+
+    for change in changes.iter() {
+        let config = scheduler.config();
+        let (mut boost, mut io_bump, mut io_stay) = (
+            config.priority_boost_interval(),
+            config.io_bump(),
+            config.io_stay(),
+        );
+        match change {
+            Change::Boost(value) => boost = *value,
+            Change::IoBump(value) => io_bump = *value,
+            Change::IoStay(value) => io_stay = *value,
+            _ => {}
+        }
+        scheduler.set_config(SchedulerConfig::new(boost, io_bump, io_stay));
+
+        for level in 0..levels {
+            let current = scheduler.queues()[level].config();
+            let mut quantum = current.quantum();
+            let mut allotment = current.allotment();
+            let mut push_front = current.push_front();
+            let mut aging = current.aging_threshold();
+            let mut discipline = current.discipline();
+            match change {
+                Change::Quantums(values) => quantum = values[level],
+                Change::Allotments(values) => allotment = values[level],
+                Change::PushFront(value) => push_front = *value,
+                Change::Aging(values) => aging = values[level],
+                Change::Disciplines(values) => discipline = values[level],
+                _ => continue,
+            }
+
+            let mut config = QueueConfig::new(quantum, allotment, push_front);
+            config.set_aging(aging, current.aging_target());
+            config.set_discipline(discipline);
+            scheduler.reconfigure_queue(level, config);
+        }
+    }
+
+    true
+}
+
+// Run to the fork time, then run the original and the changed copy to the end.
+// Returns the outcomes of both, None if the scheduler cannot be forked or changed.
+pub fn run(mut scheduler: Scheduler, at: u32, changes: &[Change]) -> Option<(Outcome, Outcome)> {
+    scheduler.set_trace(false);
+    while !scheduler.is_finished() && scheduler.current_time() < at {
+        scheduler.run_tick();
+    }
+
+    let mut changed = scheduler.fork()?;
+    if !apply(&mut changed, changes) {
+        return None;
+    }
+
+    println!(
+        "Forked at {} with {} jobs finished.",
+        scheduler.current_time(),
+        scheduler.finished_processes().len()
+    );
+
+    scheduler.run_to_completion();
+    changed.run_to_completion();
+    Some((
+        Outcome::new("original", &scheduler),
+        Outcome::new("changed", &changed),
+    ))
+}
+
+pub fn print_report(original: Outcome, changed: Outcome, items: &[String]) {
+    println!("Changed: {}.", items.join(", "));
+    compare::print_report(&[original, changed], 0);
+}
@@ -0,0 +1,150 @@
+// ffi_header.rs
+// Compile a C program against include/mlfq.h and link it with the cdylib, so the header
+// cannot drift from src/ffi.rs. The program prints the struct sizes, events and metrics,
+// which must match the same calls made from Rust.
+// Author: Hank Bao
+
+#![cfg(target_os = "linux")]
+
+use std::fmt::Write;
+use std::mem::size_of;
+use std::path::Path;
+use std::process::Command;
+
+use mlfq::ffi::*;
+
+const PROGRAM: &str = r#"
+#include <stdio.h>
+#include "mlfq.h"
+
+int main(void) {
+    printf("%zu %zu %zu\n", sizeof(MlfqQueueConfig), sizeof(MlfqMetrics), sizeof(MlfqEvent));
+
+    MlfqQueueConfig queues[] = {{10, 20, false}, {20, 40, false}, {40, 1000, false}};
+    MlfqScheduler *s = mlfq_create(queues, 3, 100, false, false);
+    if (mlfq_add_job(s, 0, 100, 0, 0) != 0 || mlfq_add_job(s, 5, 60, 10, 5) != 1)
+        return 1;
+    if (mlfq_step(s) != 1 || mlfq_run(s) != 0)
+        return 1;
+
+    MlfqEvent event;
+    while (mlfq_next_event(s, &event) == 1)
+        printf("%u %u %u %u\n", event.kind, event.pid, event.time, event.length);
+
+    MlfqMetrics m;
+    if (mlfq_metrics(s, &m) != 0)
+        return 1;
+    printf("%u %u %u %u %u %u %u %u\n", mlfq_current_time(s), m.idle_time,
+           m.average_turnaround_time, m.average_response_time, m.makespan,
+           m.p99_turnaround_time, m.max_wait_time, m.deadline_misses);
+    mlfq_destroy(s);
+    return 0;
+}
+"#;
+
+// The same calls as the C program
+fn expected() -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{} {} {}",
+        size_of::<MlfqQueueConfig>(),
+        size_of::<MlfqMetrics>(),
+        size_of::<MlfqEvent>()
+    )
+    .unwrap();
+
+    unsafe {
+        let queues = [
+            MlfqQueueConfig {
+                quantum: 10,
+                allotment: 20,
+                push_front: false,
+            },
+            MlfqQueueConfig {
+                quantum: 20,
+                allotment: 40,
+                push_front: false,
+            },
+            MlfqQueueConfig {
+                quantum: 40,
+                allotment: 1000,
+                push_front: false,
+            },
+        ];
+        let s = mlfq_create(queues.as_ptr(), queues.len(), 100, false, false);
+        assert_eq!(mlfq_add_job(s, 0, 100, 0, 0), 0);
+        assert_eq!(mlfq_add_job(s, 5, 60, 10, 5), 1);
+        assert_eq!(mlfq_step(s), 1);
+        assert_eq!(mlfq_run(s), 0);
+
+        let mut event = MlfqEvent::default();
+        while mlfq_next_event(s, &mut event) == 1 {
+            writeln!(
+                out,
+                "{} {} {} {}",
+                event.kind, event.pid, event.time, event.length
+            )
+            .unwrap();
+        }
+
+        let mut m = MlfqMetrics::default();
+        assert_eq!(mlfq_metrics(s, &mut m), 0);
+        writeln!(
+            out,
+            "{} {} {} {} {} {} {} {}",
+            mlfq_current_time(s),
+            m.idle_time,
+            m.average_turnaround_time,
+            m.average_response_time,
+            m.makespan,
+            m.p99_turnaround_time,
+            m.max_wait_time,
+            m.deadline_misses
+        )
+        .unwrap();
+        mlfq_destroy(s);
+    }
+    out
+}
+
+#[test]
+fn header_matches_cdylib() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let build = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    // The test runs from target/<profile>/deps, next to which the cdylib is built
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().and_then(|d| d.parent()).unwrap();
+    assert!(
+        lib_dir.join("libmlfq.so").exists(),
+        "libmlfq.so not found in {}",
+        lib_dir.display()
+    );
+
+    let source = build.join("mlfq_header.c");
+    let program = build.join("mlfq_header");
+    std::fs::write(&source, PROGRAM).unwrap();
+
+    let status = match Command::new("cc")
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(root.join("include"))
+        .arg(&source)
+        .arg("-L")
+        .arg(lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .args(["-lmlfq", "-o"])
+        .arg(&program)
+        .status()
+    {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("Skipped, no C compiler: {}", e);
+            return;
+        }
+    };
+    assert!(status.success(), "include/mlfq.h does not compile");
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(output.status.success(), "the C program failed");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected());
+}